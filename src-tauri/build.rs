@@ -1,3 +1,5 @@
 fn main() {
+    // Parser-only builds (the fuzz targets) have no Tauri app to configure
+    #[cfg(feature = "app")]
     tauri_build::build()
 }
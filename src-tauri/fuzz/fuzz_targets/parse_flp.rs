@@ -0,0 +1,16 @@
+#![no_main]
+
+use std::io::Cursor;
+
+use flp_vault::parser::{self, dump};
+use libfuzzer_sys::fuzz_target;
+
+// Everything that reads untrusted project bytes: the metadata parser, the
+// lossless event reader with its scope tree, and the debug dump.
+fuzz_target!(|data: &[u8]| {
+    let _ = parser::parse_flp_reader(Cursor::new(data));
+    if let Ok((_, events)) = parser::read_events(data) {
+        parser::group_events(events.map_while(Result::ok));
+    }
+    let _ = dump::dump_flp(data);
+});
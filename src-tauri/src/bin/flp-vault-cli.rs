@@ -0,0 +1,11 @@
+//! Console build of the headless subcommands. The app itself is a GUI program
+//! on Windows, so it has no console to print to or return an exit code through.
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let code = flp_vault::cli::run(&args).unwrap_or_else(|| {
+        eprintln!("{}", flp_vault::cli::USAGE);
+        2
+    });
+    std::process::exit(code);
+}
@@ -0,0 +1,46 @@
+use crate::parser::dump::dump_file;
+use std::path::Path;
+
+pub const USAGE: &str = "Usage: flp-vault-cli dump [--json] <file.flp>";
+
+/// Run a headless subcommand if one was given. Returns the exit code, or None
+/// if `args` names no subcommand.
+pub fn run(args: &[String]) -> Option<i32> {
+    match args.first().map(String::as_str) {
+        Some("dump") => Some(dump(&args[1..])),
+        _ => None,
+    }
+}
+
+fn dump(args: &[String]) -> i32 {
+    let mut format = "text";
+    let mut path = None;
+    for arg in args {
+        match arg.as_str() {
+            "--json" => format = "json",
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                return 0;
+            }
+            _ if path.is_none() => path = Some(arg),
+            _ => {
+                eprintln!("{}", USAGE);
+                return 2;
+            }
+        }
+    }
+    let Some(path) = path else {
+        eprintln!("{}", USAGE);
+        return 2;
+    };
+    match dump_file(Path::new(path), format) {
+        Ok(out) => {
+            print!("{}", out);
+            0
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
@@ -4,5 +4,5 @@ use tauri::State;
 
 #[tauri::command]
 pub fn list_scanned_files(state: State<'_, AppState>) -> Result<Vec<FileRecord>, String> {
-    Ok(list_all_files(&state.db))
+    list_all_files(&state.db).map_err(|e| e.to_string())
 }
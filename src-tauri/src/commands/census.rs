@@ -0,0 +1,13 @@
+use crate::state::AppState;
+use crate::store::unknown_events::{self, UnknownEventSummary};
+use tauri::State;
+
+/// Event IDs the parser skips, across the library or for one FL Studio version.
+#[tauri::command]
+pub fn list_unknown_events(
+    fl_version: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<UnknownEventSummary>, String> {
+    unknown_events::list_unknown_events(&state.db, fl_version.as_deref())
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,9 @@
+use crate::parser::dump::dump_file;
+use std::path::Path;
+
+/// Every event of one FLP file, for diagnosing wrong metadata. `format` is
+/// "text" (default) or "json".
+#[tauri::command]
+pub fn dump_flp(path: String, format: Option<String>) -> Result<String, String> {
+    dump_file(Path::new(&path), format.as_deref().unwrap_or("text"))
+}
@@ -8,9 +8,10 @@ use tauri::State;
 #[tauri::command]
 pub fn propose_groups(state: State<'_, AppState>) -> Result<Vec<ProposedGroup>, String> {
     let threshold = settings::get_setting(&state.db, "grouping_threshold")
+        .map_err(|e| e.to_string())?
         .and_then(|s| s.parse::<f32>().ok())
         .unwrap_or(0.65);
-    Ok(grouper::run_grouper(&state.db, threshold))
+    grouper::run_grouper(&state.db, threshold).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -18,15 +19,15 @@ pub fn confirm_groups(
     groups_input: Vec<GroupConfirmation>,
     state: State<'_, AppState>,
 ) -> Result<(), String> {
-    groups::confirm_groups(&state.db, &groups_input)
+    groups::confirm_groups(&state.db, &groups_input).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub fn list_groups(state: State<'_, AppState>) -> Result<Vec<groups::ConfirmedGroup>, String> {
-    Ok(groups::list_confirmed_groups(&state.db))
+    groups::list_confirmed_groups(&state.db).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub fn reset_groups(state: State<'_, AppState>) -> Result<(), String> {
-    groups::clear_all_groups(&state.db)
+    groups::clear_all_groups(&state.db).map_err(|e| e.to_string())
 }
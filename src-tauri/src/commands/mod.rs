@@ -1,9 +1,19 @@
 pub mod browse;
+pub mod census;
+pub mod dump;
 pub mod groups;
+pub mod plugins;
+pub mod samples;
 pub mod scan;
 pub mod settings;
+pub mod warnings;
 
 pub use browse::list_scanned_files;
+pub use census::list_unknown_events;
+pub use dump::dump_flp;
 pub use groups::{confirm_groups, list_groups, propose_groups, reset_groups};
+pub use plugins::{find_missing_plugins, list_plugin_usage, scan_installed_plugins};
+pub use samples::find_missing_samples;
 pub use scan::{cancel_scan, scan_folder};
 pub use settings::{get_settings, save_settings};
+pub use warnings::{list_files_with_warning, list_warning_codes};
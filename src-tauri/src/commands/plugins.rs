@@ -0,0 +1,29 @@
+use crate::services::plugins::{self as plugin_service, PluginScanResult, ProjectPluginReport};
+use crate::state::AppState;
+use crate::store::plugins::{self, PluginUsage};
+use crate::store::settings::get_all_settings;
+use std::path::Path;
+use tauri::State;
+
+#[tauri::command]
+pub fn list_plugin_usage(state: State<'_, AppState>) -> Result<Vec<PluginUsage>, String> {
+    plugins::list_plugin_usage(&state.db).map_err(|e| e.to_string())
+}
+
+/// Rescan the configured plugin folders and manifest into the installed-plugin table.
+#[tauri::command]
+pub fn scan_installed_plugins(state: State<'_, AppState>) -> Result<PluginScanResult, String> {
+    let settings = get_all_settings(&state.db).map_err(|e| e.to_string())?;
+    let manifest = Some(settings.plugin_manifest.as_str())
+        .filter(|m| !m.is_empty())
+        .map(Path::new);
+    plugin_service::refresh_installed_plugins(&state.db, &settings.plugin_folders, manifest)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn find_missing_plugins(
+    state: State<'_, AppState>,
+) -> Result<Vec<ProjectPluginReport>, String> {
+    plugin_service::find_missing_plugins(&state.db).map_err(|e| e.to_string())
+}
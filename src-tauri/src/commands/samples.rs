@@ -0,0 +1,13 @@
+use crate::services::samples::{self, FlFolders, ProjectSampleReport};
+use crate::state::AppState;
+use crate::store::settings::get_all_settings;
+use tauri::State;
+
+#[tauri::command]
+pub fn find_missing_samples(
+    state: State<'_, AppState>,
+) -> Result<Vec<ProjectSampleReport>, String> {
+    let settings = get_all_settings(&state.db).map_err(|e| e.to_string())?;
+    samples::find_missing_samples(&state.db, &FlFolders::from_settings(&settings))
+        .map_err(|e| e.to_string())
+}
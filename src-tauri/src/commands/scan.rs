@@ -1,5 +1,6 @@
 use crate::services::scanner;
 use crate::state::AppState;
+use crate::store::settings::get_all_settings;
 use std::sync::Arc;
 use tauri::{AppHandle, State};
 
@@ -10,6 +11,8 @@ pub fn scan_folder(
     app_handle: AppHandle,
 ) -> Result<(), String> {
     let db = Arc::clone(&state.db);
+    let settings = get_all_settings(&db).map_err(|e| e.to_string())?;
+    let workers = scanner::worker_count(settings.scan_workers);
 
     let running_flag = {
         let status = state.scan_status.lock().unwrap();
@@ -21,7 +24,7 @@ pub fn scan_folder(
     };
 
     std::thread::spawn(move || {
-        scanner::run_scan(&path, db, app_handle, running_flag);
+        scanner::run_scan(&path, db, app_handle, running_flag, workers);
     });
 
     Ok(())
@@ -11,7 +11,7 @@ pub struct SettingsValidation {
 
 #[tauri::command]
 pub fn get_settings(state: State<'_, AppState>) -> Result<Settings, String> {
-    Ok(get_all_settings(&state.db))
+    get_all_settings(&state.db).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -40,6 +40,26 @@ pub fn save_settings(
             settings.originals_folder
         ));
     }
+    for folder in &settings.plugin_folders {
+        if !folder.is_empty() && !Path::new(folder).exists() {
+            warnings.push(format!("Plugin folder does not exist: {}", folder));
+        }
+    }
+    if !settings.plugin_manifest.is_empty() && !Path::new(&settings.plugin_manifest).is_file() {
+        warnings.push(format!(
+            "Plugin manifest does not exist: {}",
+            settings.plugin_manifest
+        ));
+    }
+
+    for (label, folder) in [
+        ("FL Studio install", &settings.fl_factory_data_folder),
+        ("FL Studio user data", &settings.fl_user_data_folder),
+    ] {
+        if !folder.is_empty() && !Path::new(folder).is_dir() {
+            warnings.push(format!("{} folder does not exist: {}", label, folder));
+        }
+    }
 
     // Warn on conflicting paths
     if !settings.source_folder.is_empty()
@@ -56,9 +76,22 @@ pub fn save_settings(
     }
 
     // Persist settings
-    set_setting(&state.db, "source_folder", &settings.source_folder);
-    set_setting(&state.db, "organized_folder", &settings.organized_folder);
-    set_setting(&state.db, "originals_folder", &settings.originals_folder);
+    let plugin_folders =
+        serde_json::to_string(&settings.plugin_folders).unwrap_or_else(|_| "[]".to_string());
+    let scan_workers = settings.scan_workers.to_string();
+    let values = [
+        ("source_folder", settings.source_folder.as_str()),
+        ("organized_folder", settings.organized_folder.as_str()),
+        ("originals_folder", settings.originals_folder.as_str()),
+        ("plugin_folders", plugin_folders.as_str()),
+        ("plugin_manifest", settings.plugin_manifest.as_str()),
+        ("fl_factory_data_folder", settings.fl_factory_data_folder.as_str()),
+        ("fl_user_data_folder", settings.fl_user_data_folder.as_str()),
+        ("scan_workers", scan_workers.as_str()),
+    ];
+    for (key, value) in values {
+        set_setting(&state.db, key, value).map_err(|e| e.to_string())?;
+    }
 
     Ok(SettingsValidation { warnings })
 }
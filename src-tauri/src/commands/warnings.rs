@@ -0,0 +1,17 @@
+use crate::state::AppState;
+use crate::store::warnings::{self, WarningCodeCount};
+use tauri::State;
+
+#[tauri::command]
+pub fn list_warning_codes(state: State<'_, AppState>) -> Result<Vec<WarningCodeCount>, String> {
+    warnings::list_warning_codes(&state.db).map_err(|e| e.to_string())
+}
+
+/// Hashes of the files carrying a warning code, for filtering the library.
+#[tauri::command]
+pub fn list_files_with_warning(
+    code: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, String> {
+    warnings::hashes_with_warning(&state.db, &code).map_err(|e| e.to_string())
+}
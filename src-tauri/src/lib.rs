@@ -0,0 +1,5 @@
+//! FLP project parsing, shared by the desktop app and usable on its own for
+//! custom reports and tooling.
+
+pub mod cli;
+pub mod parser;
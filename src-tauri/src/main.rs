@@ -3,37 +3,58 @@
 
 mod commands;
 mod matcher;
-mod parser;
 mod services;
 mod state;
 mod store;
 
 use commands::{
-    cancel_scan, confirm_groups, get_settings, list_groups, list_scanned_files, propose_groups,
-    reset_groups, save_settings, scan_folder,
+    cancel_scan, confirm_groups, dump_flp, find_missing_plugins, find_missing_samples,
+    get_settings, list_files_with_warning, list_groups, list_plugin_usage, list_scanned_files,
+    list_unknown_events, list_warning_codes, propose_groups, reset_groups, save_settings,
+    scan_folder, scan_installed_plugins,
 };
+use flp_vault::parser;
 use state::AppState;
 use store::connection::init_db;
-use tauri::Manager;
+use store::Db;
+use tauri::{App, Manager};
+use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
+
+/// Open the library database, describing any failure for the user.
+fn open_library(app: &App) -> Result<Db, String> {
+    let app_data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Could not find the app data folder: {}", e))?;
+    init_db(&app_data_dir).map_err(|e| e.to_string())
+}
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    // Also available as the flp-vault-cli console program, which Windows needs
+    if let Some(code) = flp_vault::cli::run(&args) {
+        std::process::exit(code);
+    }
+
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
         .setup(|app| {
-            let app_data_dir = app
-                .path()
-                .app_data_dir()
-                .expect("Failed to resolve app data directory");
-
-            let db_mutex = init_db(&app_data_dir)
-                .expect("Failed to initialize database");
-
-            let db = db_mutex
-                .into_inner()
-                .expect("Failed to unwrap DB Mutex");
-
-            app.manage(AppState::new(db));
-
+            match open_library(app) {
+                Ok(db) => {
+                    app.manage(AppState::new(db));
+                }
+                // Without a library nothing works, so say why and quit when
+                // the message is closed rather than crash
+                Err(message) => {
+                    eprintln!("{}", message);
+                    let handle = app.handle().clone();
+                    app.dialog()
+                        .message(message)
+                        .kind(MessageDialogKind::Error)
+                        .title("FLP Vault could not open its library")
+                        .show(move |_| handle.exit(1));
+                }
+            }
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
@@ -46,6 +67,14 @@ fn main() {
             confirm_groups,
             list_groups,
             reset_groups,
+            find_missing_samples,
+            list_plugin_usage,
+            scan_installed_plugins,
+            find_missing_plugins,
+            list_warning_codes,
+            list_files_with_warning,
+            list_unknown_events,
+            dump_flp,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
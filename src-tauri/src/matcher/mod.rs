@@ -3,7 +3,7 @@ pub mod scorer;
 pub mod signals;
 pub mod union_find;
 
-use crate::store::files::FileRecord;
+use crate::store::files::{FileKind, FileRecord};
 use normalize::normalize_filename;
 use scorer::compute_confidence;
 use union_find::UnionFind;
@@ -26,20 +26,62 @@ pub fn propose_groups(files: &[FileRecord], threshold: f32) -> Vec<ProposedGroup
     let n = files.len();
     let normalized: Vec<String> = files.iter().map(|f| normalize_filename(&f.path)).collect();
 
+    // Autosaves and backups never link songs together; they are attached to
+    // the closest song afterwards
+    let secondary: Vec<bool> = files
+        .iter()
+        .map(|f| f.kind != FileKind::User)
+        .collect();
+
     let mut uf = UnionFind::new(n);
     // Track minimum edge confidence per connected component (keyed by pair)
     let mut edge_confidences: Vec<Vec<Option<f32>>> = vec![vec![None; n]; n];
+    let confidence = |i: usize, j: usize| {
+        compute_confidence(
+            &normalized[i],
+            &normalized[j],
+            files[i].bpm,
+            files[j].bpm,
+            files[i].mtime,
+            files[j].mtime,
+        )
+    };
 
     for i in 0..n {
         for j in (i + 1)..n {
-            let conf = compute_confidence(
-                &normalized[i],
-                &normalized[j],
-                files[i].bpm,
-                files[j].bpm,
-                files[i].mtime,
-                files[j].mtime,
-            );
+            if secondary[i] || secondary[j] {
+                continue;
+            }
+            let conf = confidence(i, j);
+            if conf >= threshold {
+                uf.union(i, j);
+                edge_confidences[i][j] = Some(conf);
+                edge_confidences[j][i] = Some(conf);
+            }
+        }
+    }
+
+    // Attach each secondary copy to its best-matching user save. Copies whose
+    // song is gone are grouped with each other instead.
+    let mut orphans = Vec::new();
+    for s in (0..n).filter(|&s| secondary[s]) {
+        let parent = (0..n)
+            .filter(|&p| !secondary[p])
+            .map(|p| (p, confidence(s, p)))
+            .filter(|&(_, conf)| conf >= threshold)
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+        match parent {
+            Some((p, conf)) => {
+                uf.union(s, p);
+                edge_confidences[s][p] = Some(conf);
+                edge_confidences[p][s] = Some(conf);
+            }
+            None => orphans.push(s),
+        }
+    }
+    for (k, &i) in orphans.iter().enumerate() {
+        for &j in &orphans[k + 1..] {
+            let conf = confidence(i, j);
             if conf >= threshold {
                 uf.union(i, j);
                 edge_confidences[i][j] = Some(conf);
@@ -51,7 +93,9 @@ pub fn propose_groups(files: &[FileRecord], threshold: f32) -> Vec<ProposedGroup
     let component_map = uf.groups();
     let mut groups: Vec<ProposedGroup> = Vec::new();
 
-    for (_root, members) in component_map {
+    for (_root, mut members) in component_map {
+        // User saves first, then the autosave/backup copies
+        members.sort_by_key(|&i| secondary[i]);
         let file_hashes: Vec<String> = members.iter().map(|&i| files[i].hash.clone()).collect();
         let is_ungrouped = members.len() == 1;
 
@@ -75,8 +119,11 @@ pub fn propose_groups(files: &[FileRecord], threshold: f32) -> Vec<ProposedGroup
             if min_conf == f32::MAX { threshold } else { min_conf }
         };
 
-        // Canonical name: most common normalized name, tiebreak by oldest mtime
-        let canonical_name = pick_canonical_name(&members, &normalized, files);
+        // Canonical name: most common normalized name, tiebreak by oldest mtime.
+        // Only user saves are considered when the group has any.
+        let primaries: Vec<usize> = members.iter().copied().filter(|&i| !secondary[i]).collect();
+        let named = if primaries.is_empty() { &members } else { &primaries };
+        let canonical_name = pick_canonical_name(named, &normalized, files);
 
         groups.push(ProposedGroup {
             id: Uuid::new_v4().to_string(),
@@ -134,10 +181,28 @@ mod tests {
             path: path.to_string(),
             file_size: 1000,
             mtime,
+            kind: FileKind::User,
+            header_format: None,
+            ppq: None,
             bpm,
+            time_sig_num: None,
+            time_sig_den: None,
             channel_count: Some(8),
+            song_length_bars: None,
+            song_length_secs: None,
             plugins_json: None,
+            patterns_json: None,
+            automation_json: None,
+            routing_json: None,
+            warnings_json: None,
             fl_version: None,
+            title: None,
+            artist: None,
+            genre: None,
+            comments: None,
+            url: None,
+            created_at: None,
+            time_spent_secs: None,
         }
     }
 
@@ -180,6 +245,45 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_autosaves_and_backups_attach_to_song() {
+        let copy = |hash: &str, path: &str, bpm: f64, kind: FileKind| {
+            let mut record = make_record(hash, path, Some(bpm), 1700000000);
+            record.kind = kind;
+            record
+        };
+        let autosave = copy("b", "Acid Bass Line (autosave).flp", 128.0, FileKind::Autosave);
+        let backup = copy(
+            "c",
+            "Backup/Acid Bass Line (overwritten at 1432h05).flp",
+            128.0,
+            FileKind::Backup,
+        );
+        let orphan_a = copy("e", "Gone Song (autosave).flp", 90.0, FileKind::Autosave);
+        let orphan_b = copy(
+            "f",
+            "Backup/Gone Song (overwritten at 0910h00).flp",
+            90.0,
+            FileKind::Backup,
+        );
+        let files = vec![
+            autosave,
+            backup,
+            make_record("a", "Acid Bass Line.flp", Some(128.0), 1700000000),
+            make_record("d", "Funky Groove.flp", Some(90.0), 1700000000),
+            orphan_a,
+            orphan_b,
+        ];
+        let groups = propose_groups(&files, 0.65);
+
+        let song = groups.iter().find(|g| g.file_hashes.contains(&"a".to_string())).unwrap();
+        assert_eq!(song.file_hashes, vec!["a", "b", "c"], "User save first, copies after");
+        assert_eq!(song.canonical_name, "acid bass line");
+        let orphans = groups.iter().find(|g| g.file_hashes.contains(&"e".to_string())).unwrap();
+        assert_eq!(orphans.file_hashes.len(), 2);
+        assert_eq!(groups.len(), 3);
+    }
+
     #[test]
     fn test_empty_input() {
         let groups = propose_groups(&[], 0.65);
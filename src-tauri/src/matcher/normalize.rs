@@ -7,6 +7,14 @@ pub fn normalize_filename(path: &str) -> String {
 
     let mut name = stem.to_lowercase();
 
+    // Strip the markers FL Studio adds to autosave and backup copies
+    let fl_markers = [" (autosave)", " (overwritten at "];
+    for marker in &fl_markers {
+        if let Some(pos) = name.find(marker) {
+            name.truncate(pos);
+        }
+    }
+
     // Strip common noise suffixes (with _ or space separator)
     let noise_suffixes = ["_final", "_old", "_backup", "_copy", " final", " old", " backup", " copy"];
     for suffix in &noise_suffixes {
@@ -74,6 +82,15 @@ mod tests {
         assert_eq!(normalize_filename("My Song_copy.flp"), "my song");
     }
 
+    #[test]
+    fn test_strip_fl_studio_copy_markers() {
+        assert_eq!(normalize_filename("Song Name (autosave).flp"), "song name");
+        assert_eq!(
+            normalize_filename("Backup/Song Name 2 (overwritten at 1432h05).flp"),
+            "song name"
+        );
+    }
+
     #[test]
     fn test_full_path_extracts_stem() {
         assert_eq!(normalize_filename("/path/to/Song Name 5.flp"), "song name");
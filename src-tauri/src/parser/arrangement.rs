@@ -0,0 +1,148 @@
+use super::types::{Arrangement, ClipKind, PlaylistClip, PlaylistTrack};
+
+/// Playlist item size before FL Studio 21.
+const ITEM_SIZE_LEGACY: usize = 32;
+
+/// Playlist item size from FL Studio 21 on (extra trailing fields).
+const ITEM_SIZE_21: usize = 60;
+
+/// Item indices above this refer to patterns; below it, to channels.
+const PATTERN_BASE: u16 = 20480;
+
+/// Playlist tracks are stored reversed from this index.
+const MAX_TRACK_INDEX: u16 = 499;
+
+/// Pick the playlist item record size. FL Studio 21 grew the record; the
+/// version string settles it when known, otherwise fall back to whichever
+/// size divides the payload evenly.
+fn item_size(payload_len: usize, fl_major: Option<u32>) -> Option<usize> {
+    match fl_major {
+        Some(major) if major >= 21 && payload_len.is_multiple_of(ITEM_SIZE_21) => {
+            Some(ITEM_SIZE_21)
+        }
+        Some(major) if major < 21 && payload_len.is_multiple_of(ITEM_SIZE_LEGACY) => {
+            Some(ITEM_SIZE_LEGACY)
+        }
+        _ if payload_len.is_multiple_of(ITEM_SIZE_LEGACY) => Some(ITEM_SIZE_LEGACY),
+        _ if payload_len.is_multiple_of(ITEM_SIZE_21) => Some(ITEM_SIZE_21),
+        _ => None,
+    }
+}
+
+/// Accumulates arrangement, playlist and track events.
+///
+/// Projects older than FL Studio 20 have a single playlist and no new
+/// arrangement event, so an implicit arrangement 0 is opened on demand.
+#[derive(Default)]
+pub(crate) struct ArrangementBuilder {
+    arrangements: Vec<Arrangement>,
+    current_track: Option<u16>,
+}
+
+impl ArrangementBuilder {
+    pub fn open(&mut self, index: u16) {
+        self.arrangements.push(Arrangement {
+            index,
+            ..Default::default()
+        });
+        self.current_track = None;
+    }
+
+    fn current_mut(&mut self) -> &mut Arrangement {
+        if self.arrangements.is_empty() {
+            self.open(0);
+        }
+        self.arrangements.last_mut().unwrap()
+    }
+
+    pub fn set_name(&mut self, name: String) {
+        if !name.is_empty() {
+            self.current_mut().name = Some(name);
+        }
+    }
+
+    /// Decode playlist items. Returns false if the payload size matches no
+    /// known record layout.
+    pub fn add_playlist(&mut self, payload: &[u8], fl_major: Option<u32>) -> bool {
+        let size = match item_size(payload.len(), fl_major) {
+            Some(s) => s,
+            None => return false,
+        };
+        let arrangement = self.current_mut();
+        for item in payload.chunks_exact(size) {
+            // Record layout: u32 position, u16 pattern base, u16 item index,
+            // u32 length, u16 reversed track index, ...
+            let position = u32::from_le_bytes([item[0], item[1], item[2], item[3]]);
+            let item_index = u16::from_le_bytes([item[6], item[7]]);
+            let length = u32::from_le_bytes([item[8], item[9], item[10], item[11]]);
+            let track_rvidx = u16::from_le_bytes([item[12], item[13]]);
+
+            let (kind, index) = if item_index > PATTERN_BASE {
+                (ClipKind::Pattern, item_index - PATTERN_BASE)
+            } else {
+                (ClipKind::Channel, item_index)
+            };
+
+            arrangement.clips.push(PlaylistClip {
+                kind,
+                index,
+                track: MAX_TRACK_INDEX.saturating_sub(track_rvidx) + 1,
+                position_ticks: position,
+                length_ticks: length,
+            });
+            arrangement.length_ticks = arrangement
+                .length_ticks
+                .max(position.saturating_add(length));
+        }
+        true
+    }
+
+    /// Track data starts with the track's 1-based index; a name may follow.
+    pub fn set_track(&mut self, payload: &[u8]) {
+        self.current_track = payload
+            .get(0..4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as u16);
+    }
+
+    pub fn set_track_name(&mut self, name: String) {
+        let index = match self.current_track {
+            Some(i) if !name.is_empty() => i,
+            _ => return,
+        };
+        self.current_mut().tracks.push(PlaylistTrack { index, name });
+    }
+
+    pub fn finish(self) -> Vec<Arrangement> {
+        self.arrangements
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_item_size_selection() {
+        assert_eq!(item_size(64, Some(20)), Some(ITEM_SIZE_LEGACY));
+        assert_eq!(item_size(120, Some(21)), Some(ITEM_SIZE_21));
+        // 480 divides by both; version decides
+        assert_eq!(item_size(480, Some(21)), Some(ITEM_SIZE_21));
+        assert_eq!(item_size(480, Some(12)), Some(ITEM_SIZE_LEGACY));
+        assert_eq!(item_size(60, None), Some(ITEM_SIZE_21));
+        assert_eq!(item_size(33, None), None);
+    }
+
+    #[test]
+    fn test_track_name_requires_track_data() {
+        let mut b = ArrangementBuilder::default();
+        b.set_track_name("orphan".to_string());
+        b.set_track(&3u32.to_le_bytes());
+        b.set_track_name("Drums".to_string());
+        let arrangements = b.finish();
+        assert_eq!(arrangements.len(), 1);
+        assert_eq!(
+            arrangements[0].tracks,
+            vec![PlaylistTrack { index: 3, name: "Drums".to_string() }]
+        );
+    }
+}
@@ -0,0 +1,54 @@
+use std::collections::BTreeMap;
+
+use super::types::UnknownEvent;
+
+/// Number of leading payload bytes kept as a sample per unknown event ID.
+pub(crate) const SAMPLE_LEN: usize = 16;
+
+/// Histogram of event IDs the parser does not handle, so new events in
+/// future FL Studio releases show up in the library report.
+#[derive(Default)]
+pub(crate) struct EventCensus {
+    events: BTreeMap<u8, UnknownEvent>,
+}
+
+impl EventCensus {
+    /// Record one unhandled event of `size` bytes. `payload` may be just the
+    /// leading bytes when the rest was skipped; the sample is taken from the
+    /// first occurrence only.
+    pub fn record(&mut self, event_id: u8, size: u32, payload: &[u8]) {
+        let entry = self.events.entry(event_id).or_insert_with(|| UnknownEvent {
+            event_id,
+            count: 0,
+            min_size: size,
+            max_size: size,
+            sample: payload[..payload.len().min(SAMPLE_LEN)].to_vec(),
+        });
+        entry.count += 1;
+        entry.min_size = entry.min_size.min(size);
+        entry.max_size = entry.max_size.max(size);
+    }
+
+    pub fn finish(self) -> Vec<UnknownEvent> {
+        self.events.into_values().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_census_tracks_sizes_and_first_sample() {
+        let mut census = EventCensus::default();
+        census.record(250, 40, &[1u8; 40]);
+        census.record(250, 4, &[2u8; 4]);
+        census.record(30, 1, &[7]);
+        let events = census.finish();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].event_id, 30);
+        let e = &events[1];
+        assert_eq!((e.count, e.min_size, e.max_size), (2, 4, 40));
+        assert_eq!(e.sample, vec![1u8; SAMPLE_LEN]);
+    }
+}
@@ -0,0 +1,227 @@
+use super::plugin::PluginBuilder;
+use super::types::{AutomationClip, AutomationTarget, ChannelInfo, ChannelType};
+
+/// Offset of the point count in the automation data (234) payload.
+const AUTOMATION_POINT_COUNT_OFFSET: usize = 17;
+
+/// Size of one automation point record: f64 position, f64 value, f32
+/// tension, 4 reserved bytes.
+const AUTOMATION_POINT_SIZE: usize = 24;
+
+/// Size of one record in the remote controllers (227) payload.
+const REMOTE_CONTROLLER_SIZE: usize = 20;
+
+/// Destination flag set when the target is a mixer insert slot.
+const DEST_INSERT_SLOT: i16 = 0x2000;
+
+/// Parameter flag set when the target is a plugin parameter rather than a
+/// built-in channel/slot control.
+const PARAM_PLUGIN: u16 = 0x8000;
+
+/// Count automation points. The count is taken from the header but capped
+/// to the records actually present.
+fn decode_point_count(payload: &[u8]) -> Option<u32> {
+    let start = AUTOMATION_POINT_COUNT_OFFSET;
+    let bytes: [u8; 4] = payload.get(start..start + 4)?.try_into().ok()?;
+    let declared = u32::from_le_bytes(bytes);
+    let present = (payload.len() - start - 4) / AUTOMATION_POINT_SIZE;
+    Some(declared.min(present as u32))
+}
+
+/// Decode remote controller records: u16 reserved, u8 source channel,
+/// u32 + u8 reserved, u16 parameter, i16 destination, u64 reserved.
+fn decode_remote_controllers(payload: &[u8]) -> Vec<(u16, AutomationTarget)> {
+    payload
+        .chunks_exact(REMOTE_CONTROLLER_SIZE)
+        .map(|r| {
+            let source = r[2] as u16;
+            let param = u16::from_le_bytes([r[8], r[9]]);
+            let dest = i16::from_le_bytes([r[10], r[11]]);
+            let parameter = param & !PARAM_PLUGIN;
+            let plugin_parameter = param & PARAM_PLUGIN != 0;
+            let target = if dest & DEST_INSERT_SLOT != 0 {
+                AutomationTarget::InsertSlot {
+                    insert: ((dest >> 6) & 0x7F) as u16,
+                    slot: (dest & 0x3F) as u16,
+                    parameter,
+                    plugin_parameter,
+                }
+            } else {
+                AutomationTarget::Channel {
+                    channel: (dest & 0x1FFF) as u16,
+                    parameter,
+                    plugin_parameter,
+                }
+            };
+            (source, target)
+        })
+        .collect()
+}
+
+/// Accumulates channel rack events.
+///
+/// A channel runs from its new channel event to the next one (or the start of
+/// the mixer section). Automation clips are split out on close; their targets
+/// come from the project-level remote controller event and are linked up in
+/// `finish`.
+#[derive(Default)]
+pub(crate) struct ChannelBuilder {
+    current: ChannelInfo,
+    plugin: PluginBuilder,
+    point_count: u32,
+    open: bool,
+    channels: Vec<ChannelInfo>,
+    automation: Vec<AutomationClip>,
+    targets: Vec<(u16, AutomationTarget)>,
+}
+
+impl ChannelBuilder {
+    /// Close the current channel and start a new one.
+    pub fn open(&mut self, index: u16) {
+        self.close();
+        self.current.index = index;
+        self.open = true;
+    }
+
+    /// Close the current channel, if any. Called when the mixer section starts.
+    pub fn close(&mut self) {
+        if !self.open {
+            return;
+        }
+        self.open = false;
+        let (plugin_name, plugin) = self.plugin.take();
+        let mut channel = std::mem::take(&mut self.current);
+        let point_count = std::mem::take(&mut self.point_count);
+        channel.plugin_name = plugin_name;
+        channel.plugin = plugin;
+
+        match channel.channel_type {
+            ChannelType::Automation => self.automation.push(AutomationClip {
+                channel: channel.index,
+                name: channel.name,
+                point_count,
+                target: None,
+            }),
+            // Audio clips use the native generator type but host no plugin
+            ChannelType::Generator
+                if channel.plugin.is_none() && !channel.sample_paths.is_empty() =>
+            {
+                channel.channel_type = ChannelType::AudioClip;
+                self.channels.push(channel);
+            }
+            _ => self.channels.push(channel),
+        }
+    }
+
+    pub fn set_type(&mut self, value: u8) {
+        self.current.channel_type = ChannelType::from_raw(value);
+    }
+
+    pub fn set_insert(&mut self, value: u8) {
+        self.current.insert = Some(value as u16);
+    }
+
+    /// Some older files name a channel without a preceding new channel event.
+    pub fn set_name(&mut self, name: String) {
+        self.current.name = name;
+        self.open = true;
+    }
+
+    pub fn add_sample_path(&mut self, path: String) {
+        if !path.is_empty() {
+            self.current.sample_paths.push(path);
+        }
+    }
+
+    pub fn plugin_mut(&mut self) -> &mut PluginBuilder {
+        &mut self.plugin
+    }
+
+    pub fn set_automation(&mut self, payload: &[u8]) {
+        if let Some(count) = decode_point_count(payload) {
+            self.point_count = count;
+        }
+    }
+
+    pub fn add_remote_controllers(&mut self, payload: &[u8]) {
+        self.targets.extend(decode_remote_controllers(payload));
+    }
+
+    /// Returns the sound-producing channels and the automation clips.
+    pub fn finish(mut self) -> (Vec<ChannelInfo>, Vec<AutomationClip>) {
+        self.close();
+        for (source, target) in self.targets {
+            if let Some(clip) = self.automation.iter_mut().find(|c| c.channel == source) {
+                clip.target = Some(target);
+            }
+        }
+        (self.channels, self.automation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn remote_controller(source: u8, param: u16, dest: i16) -> Vec<u8> {
+        let mut v = vec![0u8; 2];
+        v.push(source);
+        v.extend_from_slice(&[0u8; 5]);
+        v.extend_from_slice(&param.to_le_bytes());
+        v.extend_from_slice(&dest.to_le_bytes());
+        v.extend_from_slice(&[0u8; 8]);
+        v
+    }
+
+    #[test]
+    fn test_point_count_capped_to_payload() {
+        let mut payload = vec![0u8; AUTOMATION_POINT_COUNT_OFFSET];
+        payload.extend_from_slice(&1000u32.to_le_bytes());
+        payload.extend_from_slice(&[0u8; AUTOMATION_POINT_SIZE * 3]);
+        assert_eq!(decode_point_count(&payload), Some(3));
+        assert_eq!(decode_point_count(&[0u8; 8]), None);
+    }
+
+    #[test]
+    fn test_remote_controller_targets() {
+        let mut payload = remote_controller(1, 0x8000 | 12, 3);
+        payload.extend(remote_controller(2, 5, DEST_INSERT_SLOT | (4 << 6) | 2));
+        let targets = decode_remote_controllers(&payload);
+        assert_eq!(
+            targets,
+            vec![
+                (
+                    1,
+                    AutomationTarget::Channel { channel: 3, parameter: 12, plugin_parameter: true }
+                ),
+                (
+                    2,
+                    AutomationTarget::InsertSlot {
+                        insert: 4,
+                        slot: 2,
+                        parameter: 5,
+                        plugin_parameter: false,
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_audio_clip_and_automation_split() {
+        let mut b = ChannelBuilder::default();
+        b.open(0);
+        b.set_type(2);
+        b.add_sample_path("vocal.wav".to_string());
+        b.open(1);
+        b.set_type(5);
+        b.set_name("Filter cutoff".to_string());
+        b.add_remote_controllers(&remote_controller(1, 0, 0));
+        let (channels, automation) = b.finish();
+        assert_eq!(channels.len(), 1);
+        assert_eq!(channels[0].channel_type, ChannelType::AudioClip);
+        assert_eq!(automation.len(), 1);
+        assert_eq!(automation[0].channel, 1);
+        assert!(automation[0].target.is_some());
+    }
+}
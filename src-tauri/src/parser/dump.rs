@@ -0,0 +1,243 @@
+use std::fmt::Write;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use serde::Serialize;
+
+use super::events::*;
+use super::flp::{decode_string, ParseError};
+
+/// Payload bytes shown in the hex view. The full size is always reported.
+const HEX_LIMIT: usize = 256;
+
+/// Bytes per line in the text report's hex view.
+const HEX_LINE: usize = 16;
+
+/// One event as shown in a dump.
+#[derive(Debug, Clone, Serialize)]
+pub struct DumpEvent {
+    pub offset: u64,
+    pub id: u8,
+    pub name: Option<&'static str>,
+    pub kind: EventKind,
+    pub size: usize,
+    /// Human-readable value for numeric and text events.
+    pub value: Option<String>,
+    /// Space-separated hex of the first `HEX_LIMIT` payload bytes.
+    pub hex: String,
+    #[serde(skip)]
+    preview: Vec<u8>,
+}
+
+/// Every event of a file in stream order, for debugging parse results.
+#[derive(Debug, Clone, Serialize)]
+pub struct FlpDump {
+    pub header: FlpHeader,
+    pub events: Vec<DumpEvent>,
+    /// Why the event stream ended early, if it did.
+    pub error: Option<String>,
+}
+
+/// Events whose payload is a string.
+fn is_text_event(event_id: u8) -> bool {
+    matches!(
+        event_id,
+        FLP_TEXT_CHAN_NAME
+            | FLP_TEXT_PAT_NAME
+            | FLP_TEXT_TITLE
+            | FLP_TEXT_COMMENT
+            | FLP_TEXT_SAMPLE_PATH
+            | FLP_TEXT_URL
+            | FLP_TEXT_COMMENT_RTF
+            | FLP_VERSION
+            | FLP_TEXT_PLUGIN_NAME
+            | FLP_TEXT_PLUGIN_DISPLAY_NAME
+            | FLP_TEXT_INSERT_NAME
+            | FLP_TEXT_GENRE
+            | FLP_TEXT_ARTISTS
+            | FLP_TEXT_TRACK_NAME
+            | FLP_TEXT_ARRANGEMENT_NAME
+    )
+}
+
+/// Decoded value of an event, with units where the raw number is scaled.
+fn describe_value(event: &Event) -> Option<String> {
+    if let Some(value) = event.value() {
+        return Some(match event.id {
+            FLP_TEMPO => format!("{} ({:.3} BPM)", value, value as f64 / 1000.0),
+            FLP_TEMPO_LEGACY => format!("{} BPM", value),
+            FLP_PAT_COLOR => format!("{:#08x}", value),
+            _ => value.to_string(),
+        });
+    }
+    if is_text_event(event.id) {
+        return Some(format!("{:?}", decode_string(&event.payload)));
+    }
+    None
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .take(HEX_LIMIT)
+        .map(|b| format!("{:02x}", b))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Dump the header and every event of an FLP stream positioned at the start
+/// of the file. A truncated event ends the dump with `error` set.
+pub fn dump_flp<R: Read>(reader: R) -> Result<FlpDump, ParseError> {
+    let (header, reader) = read_events(reader)?;
+    let mut dump = FlpDump {
+        header,
+        events: Vec::new(),
+        error: None,
+    };
+    for event in reader {
+        let event = match event {
+            Ok(e) => e,
+            Err(e) => {
+                dump.error = Some(e.to_string());
+                break;
+            }
+        };
+        dump.events.push(DumpEvent {
+            offset: event.offset,
+            id: event.id,
+            name: event_name(event.id),
+            kind: event.kind,
+            size: event.payload.len(),
+            value: describe_value(&event),
+            hex: hex(&event.payload),
+            preview: event.payload[..event.payload.len().min(HEX_LIMIT)].to_vec(),
+        });
+    }
+    Ok(dump)
+}
+
+/// Output formats accepted by `dump_file`.
+pub const DUMP_FORMATS: [&str; 2] = ["text", "json"];
+
+/// Dump every event of an FLP file as a hex-annotated text report or as
+/// pretty-printed JSON.
+pub fn dump_file(path: &Path, format: &str) -> Result<String, String> {
+    if !DUMP_FORMATS.contains(&format) {
+        return Err(format!("Unknown dump format '{}' (expected text or json)", format));
+    }
+    let file = File::open(path).map_err(|e| format!("Cannot open {}: {}", path.display(), e))?;
+    let dump =
+        dump_flp(BufReader::new(file)).map_err(|e| format!("{}: {}", path.display(), e))?;
+    if format == "json" {
+        serde_json::to_string_pretty(&dump).map_err(|e| e.to_string())
+    } else {
+        Ok(dump.to_text())
+    }
+}
+
+impl FlpDump {
+    /// Plain-text report: one line per event followed by an indented hex and
+    /// ASCII view of its payload.
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "FLP format {:#04x}, {} channels, PPQ {}, {} events",
+            self.header.format,
+            self.header.channel_count,
+            self.header.ppq,
+            self.events.len()
+        );
+        let _ = writeln!(out, "{:<10} {:>3}  {:<26} {:>8}  VALUE", "OFFSET", "ID", "NAME", "SIZE");
+        for event in &self.events {
+            let _ = writeln!(
+                out,
+                "{:#010x} {:>3}  {:<26} {:>8}  {}",
+                event.offset,
+                event.id,
+                event.name.unwrap_or("?"),
+                event.size,
+                event.value.as_deref().unwrap_or("")
+            );
+            for (i, line) in event.preview.chunks(HEX_LINE).enumerate() {
+                let hex: Vec<String> = line.iter().map(|b| format!("{:02x}", b)).collect();
+                let ascii: String = line
+                    .iter()
+                    .map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' })
+                    .collect();
+                let _ = writeln!(
+                    out,
+                    "           {:04x}: {:<width$}  |{}|",
+                    i * HEX_LINE,
+                    hex.join(" "),
+                    ascii,
+                    width = HEX_LINE * 3 - 1
+                );
+            }
+            if event.size > HEX_LIMIT {
+                let _ = writeln!(out, "           ... {} more bytes", event.size - HEX_LIMIT);
+            }
+        }
+        if let Some(error) = &self.error {
+            let _ = writeln!(out, "Stopped: {}", error);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dump_names_values_and_hex() {
+        let mut data = b"FLhd".to_vec();
+        data.extend_from_slice(&6u32.to_le_bytes());
+        for field in [0u16, 1, 96] {
+            data.extend_from_slice(&field.to_le_bytes());
+        }
+        let events = [
+            vec![FLP_TEMPO, 0xe0, 0x22, 0x02, 0x00],
+            vec![FLP_TEXT_TITLE, 4, b'S', b'o', b'n', b'g'],
+            vec![250, 2, 0xAB, 0xCD],
+        ]
+        .concat();
+        data.extend_from_slice(b"FLdt");
+        data.extend_from_slice(&(events.len() as u32).to_le_bytes());
+        data.extend(events);
+
+        let dump = dump_flp(&data[..]).expect("should dump");
+        assert!(dump.error.is_none());
+        assert_eq!(dump.events.len(), 3);
+        assert_eq!(dump.events[0].name, Some("TEMPO"));
+        assert_eq!(dump.events[0].value.as_deref(), Some("140000 (140.000 BPM)"));
+        assert_eq!(dump.events[1].value.as_deref(), Some("\"Song\""));
+        assert_eq!(dump.events[2].name, None);
+        assert_eq!(dump.events[2].hex, "ab cd");
+
+        let text = dump.to_text();
+        assert!(text.contains("0x00000016 156  TEMPO"));
+        assert!(text.contains("|Song|"));
+    }
+
+    #[test]
+    fn test_dump_file_formats() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("song.flp");
+        let mut data = b"FLhd".to_vec();
+        data.extend_from_slice(&[6, 0, 0, 0, 0, 0, 1, 0, 96, 0]);
+        data.extend_from_slice(b"FLdt");
+        data.extend_from_slice(&5u32.to_le_bytes());
+        data.extend_from_slice(&[156, 0xe0, 0x22, 0x02, 0x00]);
+        std::fs::write(&path, &data).unwrap();
+
+        let text = dump_file(&path, "text").unwrap();
+        assert!(text.contains("140.000 BPM"));
+        let json: serde_json::Value =
+            serde_json::from_str(&dump_file(&path, "json").unwrap()).unwrap();
+        assert_eq!(json["events"][0]["name"], "TEMPO");
+        assert!(dump_file(&path, "xml").is_err());
+        assert!(dump_file(&dir.path().join("missing.flp"), "text").is_err());
+    }
+}
@@ -1,20 +1,99 @@
 use std::io::{self, Read};
 
+use byteorder::{LittleEndian, ReadBytesExt};
+use serde::Serialize;
+
+use super::flp::ParseError;
+
 // BYTE events (0-63): 1 byte value
+pub const FLP_TIME_SIG_NUM: u8 = 17;
+pub const FLP_TIME_SIG_BEAT: u8 = 18;
 pub const FLP_CHAN_TYPE: u8 = 21;
+pub const FLP_CHAN_ROUTED_TO: u8 = 22;
 
 // WORD events (64-127): 2 byte LE value
 pub const FLP_NEW_CHAN: u8 = 64;
 pub const FLP_NEW_PAT: u8 = 65;
 pub const FLP_TEMPO_LEGACY: u8 = 66;
+pub const FLP_SLOT_INDEX: u8 = 98;
+pub const FLP_NEW_ARRANGEMENT: u8 = 99;
 
 // DWORD events (128-191): 4 byte LE value
+pub const FLP_INSERT_OUTPUT: u8 = 147;
+pub const FLP_PAT_COLOR: u8 = 150;
 pub const FLP_TEMPO: u8 = 156;
 
 // TEXT/VARIABLE events (192-255): varint length + bytes
 pub const FLP_TEXT_CHAN_NAME: u8 = 192;
+pub const FLP_TEXT_PAT_NAME: u8 = 193;
+pub const FLP_TEXT_TITLE: u8 = 194;
+pub const FLP_TEXT_COMMENT: u8 = 195;
+pub const FLP_TEXT_SAMPLE_PATH: u8 = 196;
+pub const FLP_TEXT_URL: u8 = 197;
+pub const FLP_TEXT_COMMENT_RTF: u8 = 198;
 pub const FLP_VERSION: u8 = 199;
 pub const FLP_TEXT_PLUGIN_NAME: u8 = 201;
+pub const FLP_TEXT_PLUGIN_DISPLAY_NAME: u8 = 203;
+pub const FLP_TEXT_INSERT_NAME: u8 = 204;
+pub const FLP_TEXT_GENRE: u8 = 206;
+pub const FLP_TEXT_ARTISTS: u8 = 207;
+pub const FLP_PLUGIN_STATE: u8 = 213;
+pub const FLP_PAT_NOTES: u8 = 224;
+pub const FLP_MIXER_PARAMS: u8 = 225;
+pub const FLP_REMOTE_CONTROLLERS: u8 = 227;
+pub const FLP_PLAYLIST: u8 = 233;
+pub const FLP_AUTOMATION_DATA: u8 = 234;
+pub const FLP_INSERT_ROUTING: u8 = 235;
+pub const FLP_INSERT_FLAGS: u8 = 236;
+pub const FLP_TIMESTAMP: u8 = 237;
+pub const FLP_TRACK_DATA: u8 = 238;
+pub const FLP_TEXT_TRACK_NAME: u8 = 239;
+pub const FLP_TEXT_ARRANGEMENT_NAME: u8 = 241;
+
+/// Name of a known event ID, as used in event dumps.
+pub fn event_name(event_id: u8) -> Option<&'static str> {
+    let name = match event_id {
+        FLP_TIME_SIG_NUM => "TIME_SIG_NUM",
+        FLP_TIME_SIG_BEAT => "TIME_SIG_BEAT",
+        FLP_CHAN_TYPE => "CHAN_TYPE",
+        FLP_CHAN_ROUTED_TO => "CHAN_ROUTED_TO",
+        FLP_NEW_CHAN => "NEW_CHAN",
+        FLP_NEW_PAT => "NEW_PAT",
+        FLP_TEMPO_LEGACY => "TEMPO_LEGACY",
+        FLP_SLOT_INDEX => "SLOT_INDEX",
+        FLP_NEW_ARRANGEMENT => "NEW_ARRANGEMENT",
+        FLP_INSERT_OUTPUT => "INSERT_OUTPUT",
+        FLP_PAT_COLOR => "PAT_COLOR",
+        FLP_TEMPO => "TEMPO",
+        FLP_TEXT_CHAN_NAME => "TEXT_CHAN_NAME",
+        FLP_TEXT_PAT_NAME => "TEXT_PAT_NAME",
+        FLP_TEXT_TITLE => "TEXT_TITLE",
+        FLP_TEXT_COMMENT => "TEXT_COMMENT",
+        FLP_TEXT_SAMPLE_PATH => "TEXT_SAMPLE_PATH",
+        FLP_TEXT_URL => "TEXT_URL",
+        FLP_TEXT_COMMENT_RTF => "TEXT_COMMENT_RTF",
+        FLP_VERSION => "VERSION",
+        FLP_TEXT_PLUGIN_NAME => "TEXT_PLUGIN_NAME",
+        FLP_TEXT_PLUGIN_DISPLAY_NAME => "TEXT_PLUGIN_DISPLAY_NAME",
+        FLP_TEXT_INSERT_NAME => "TEXT_INSERT_NAME",
+        FLP_TEXT_GENRE => "TEXT_GENRE",
+        FLP_TEXT_ARTISTS => "TEXT_ARTISTS",
+        FLP_PLUGIN_STATE => "PLUGIN_STATE",
+        FLP_PAT_NOTES => "PAT_NOTES",
+        FLP_MIXER_PARAMS => "MIXER_PARAMS",
+        FLP_REMOTE_CONTROLLERS => "REMOTE_CONTROLLERS",
+        FLP_PLAYLIST => "PLAYLIST",
+        FLP_AUTOMATION_DATA => "AUTOMATION_DATA",
+        FLP_INSERT_ROUTING => "INSERT_ROUTING",
+        FLP_INSERT_FLAGS => "INSERT_FLAGS",
+        FLP_TIMESTAMP => "TIMESTAMP",
+        FLP_TRACK_DATA => "TRACK_DATA",
+        FLP_TEXT_TRACK_NAME => "TEXT_TRACK_NAME",
+        FLP_TEXT_ARRANGEMENT_NAME => "TEXT_ARRANGEMENT_NAME",
+        _ => return None,
+    };
+    Some(name)
+}
 
 /// Read a variable-length integer (7 bits per byte, MSB = "more bytes follow").
 /// Used for the length prefix of TEXT/VARIABLE events (event IDs 192-255).
@@ -36,3 +115,211 @@ pub fn read_varint<R: Read>(reader: &mut R) -> io::Result<u64> {
     }
     Ok(result)
 }
+
+/// Size class of an event, fixed by its ID range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    /// IDs 0-63: 1-byte value
+    Byte,
+    /// IDs 64-127: 2-byte LE value
+    Word,
+    /// IDs 128-191: 4-byte LE value
+    Dword,
+    /// IDs 192-255: varint length + payload
+    Data,
+}
+
+impl EventKind {
+    pub fn of(event_id: u8) -> Self {
+        match event_id {
+            0..=63 => EventKind::Byte,
+            64..=127 => EventKind::Word,
+            128..=191 => EventKind::Dword,
+            _ => EventKind::Data,
+        }
+    }
+
+    /// Label used in warnings: "BYTE", "WORD", "DWORD" or "TEXT".
+    pub fn label(self) -> &'static str {
+        match self {
+            EventKind::Byte => "BYTE",
+            EventKind::Word => "WORD",
+            EventKind::Dword => "DWORD",
+            EventKind::Data => "TEXT",
+        }
+    }
+}
+
+/// ID and payload length of an event, which come before its payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventHeader {
+    pub id: u8,
+    pub kind: EventKind,
+    pub len: u64,
+}
+
+/// Read the ID of the next event. `Ok(None)` is a clean end of the stream.
+pub fn read_event_id<R: Read>(reader: &mut R) -> io::Result<Option<u8>> {
+    match reader.read_u8() {
+        Ok(id) => Ok(Some(id)),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Read what follows an event ID up to its payload: nothing for fixed-size
+/// events, the varint length prefix for the rest.
+pub fn read_event_len<R: Read>(reader: &mut R, id: u8) -> io::Result<EventHeader> {
+    let kind = EventKind::of(id);
+    let len = match kind {
+        EventKind::Byte => 1,
+        EventKind::Word => 2,
+        EventKind::Dword => 4,
+        EventKind::Data => read_varint(reader)?,
+    };
+    Ok(EventHeader { id, kind, len })
+}
+
+/// One event exactly as stored. `offset` is the file position of the ID byte;
+/// `payload` is the value bytes (LE) of fixed-size events, or the data of
+/// variable-length events without the length prefix.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Event {
+    pub id: u8,
+    pub kind: EventKind,
+    pub offset: u64,
+    pub payload: Vec<u8>,
+}
+
+impl Event {
+    /// Integer value of a Byte, Word or Dword event.
+    pub fn value(&self) -> Option<u32> {
+        match self.kind {
+            EventKind::Byte => self.payload.first().map(|&b| b as u32),
+            EventKind::Word => {
+                Some(u16::from_le_bytes(self.payload.get(..2)?.try_into().ok()?) as u32)
+            }
+            EventKind::Dword => Some(u32::from_le_bytes(self.payload.get(..4)?.try_into().ok()?)),
+            EventKind::Data => None,
+        }
+    }
+}
+
+/// Counts bytes read so event offsets are known without `Seek`.
+struct CountingReader<R> {
+    inner: R,
+    position: u64,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+/// Iterator over the raw event stream, with nothing decoded or dropped.
+/// A truncated event yields one `Err` and ends the iteration.
+pub struct EventReader<R> {
+    reader: CountingReader<R>,
+    done: bool,
+}
+
+impl<R: Read> EventReader<R> {
+    /// Read events from `reader`, which must be positioned at the first event.
+    /// `offset` is that position in the file, used for `Event::offset`.
+    pub fn new(reader: R, offset: u64) -> Self {
+        EventReader {
+            reader: CountingReader { inner: reader, position: offset },
+            done: false,
+        }
+    }
+
+    fn read_event(&mut self) -> io::Result<Option<Event>> {
+        let offset = self.reader.position;
+        let id = match read_event_id(&mut self.reader)? {
+            Some(id) => id,
+            None => return Ok(None),
+        };
+        let EventHeader { kind, len, .. } = read_event_len(&mut self.reader, id)?;
+        // Grows with the data actually present, so a corrupt length cannot
+        // force a huge allocation
+        let mut payload = Vec::new();
+        (&mut self.reader).take(len).read_to_end(&mut payload)?;
+        if (payload.len() as u64) < len {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("event {} at offset {} is truncated", id, offset),
+            ));
+        }
+        Ok(Some(Event { id, kind, offset, payload }))
+    }
+}
+
+impl<R: Read> Iterator for EventReader<R> {
+    type Item = io::Result<Event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.read_event() {
+            Ok(Some(event)) => Some(Ok(event)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// Header fields of an FLP file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct FlpHeader {
+    pub format: u16,
+    pub channel_count: u16,
+    pub ppq: u16,
+}
+
+/// Read the "FLhd" header chunk.
+pub fn read_header<R: Read>(reader: &mut R) -> Result<FlpHeader, ParseError> {
+    let mut magic = [0u8; 4];
+    if reader.read_exact(&mut magic).is_err() || &magic != b"FLhd" {
+        return Err(ParseError::InvalidMagic);
+    }
+    // Header chunk size (always 6 for standard FLP)
+    let _header_size = reader
+        .read_u32::<LittleEndian>()
+        .map_err(|_| ParseError::TruncatedHeader)?;
+    let mut read_u16 = || {
+        reader
+            .read_u16::<LittleEndian>()
+            .map_err(|_| ParseError::TruncatedHeader)
+    };
+    Ok(FlpHeader {
+        format: read_u16()?,
+        channel_count: read_u16()?,
+        ppq: read_u16()?,
+    })
+}
+
+/// Read the header and return an iterator over the events of an FLP stream
+/// positioned at the start of the file. A file without an "FLdt" chunk yields
+/// no events.
+pub fn read_events<R: Read>(mut reader: R) -> Result<(FlpHeader, EventReader<R>), ParseError> {
+    let header = read_header(&mut reader)?;
+    let mut magic = [0u8; 4];
+    let has_data = reader.read_exact(&mut magic).is_ok()
+        && &magic == b"FLdt"
+        && reader.read_u32::<LittleEndian>().is_ok();
+    // 14-byte header chunk + 8-byte data chunk prefix
+    let mut events = EventReader::new(reader, 22);
+    events.done = !has_data;
+    Ok((header, events))
+}
@@ -1,8 +1,15 @@
-use std::io::{Cursor, Read};
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
 use byteorder::{LittleEndian, ReadBytesExt};
 
+use super::arrangement::ArrangementBuilder;
+use super::census::{self, EventCensus};
+use super::channel::ChannelBuilder;
 use super::events::*;
-use super::types::{ChannelInfo, FlpMetadata};
+use super::mixer::{routing_graph, MixerBuilder};
+use super::pattern::PatternBuilder;
+use super::plugin;
+use super::project::{decode_timestamp, rtf_to_plain};
+use super::types::{FlpMetadata, ParseWarning, WarningCode};
 
 #[derive(Debug)]
 pub enum ParseError {
@@ -30,7 +37,7 @@ impl From<std::io::Error> for ParseError {
 /// Decode a byte slice as a string.
 /// Detects UTF-16 LE (alternating null bytes or BOM), falls back to UTF-8.
 /// Strips trailing null bytes.
-fn decode_string(bytes: &[u8]) -> String {
+pub(crate) fn decode_string(bytes: &[u8]) -> String {
     // Check for UTF-16 LE BOM (FF FE) or alternating nulls pattern
     let is_utf16 = bytes.starts_with(&[0xFF, 0xFE])
         || (bytes.len() >= 4
@@ -57,241 +64,583 @@ fn decode_string(bytes: &[u8]) -> String {
     s.trim_end_matches('\0').to_string()
 }
 
-/// Parse an FLP file from raw bytes.
-/// Returns FlpMetadata on success or ParseError for fatal errors.
-/// For partial/truncated streams, returns Ok with warnings rather than Err.
-pub fn parse_flp(bytes: &[u8]) -> Result<FlpMetadata, ParseError> {
-    if bytes.len() < 4 {
-        return Err(ParseError::InvalidMagic);
+/// Decode a project info text event, treating an empty string as absent.
+fn decode_info_text(bytes: &[u8]) -> Option<String> {
+    let text = decode_string(bytes);
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        None
+    } else {
+        Some(trimmed.to_string())
+    }
+}
+
+/// FL Studio allows 1-16 beats per bar.
+fn is_valid_time_sig_num(num: u8) -> bool {
+    (1..=16).contains(&num)
+}
+
+/// FL Studio only offers 2, 4, 8 and 16 as the beat unit.
+fn is_valid_time_sig_den(den: u8) -> bool {
+    matches!(den, 2 | 4 | 8 | 16)
+}
+
+/// Record a time signature component on the project, or on the current
+/// pattern when one is open. Out-of-range values are dropped with a warning.
+fn apply_time_sig(
+    meta: &mut FlpMetadata,
+    patterns: &mut PatternBuilder,
+    offset: u64,
+    event_id: u8,
+    value: u8,
+) {
+    let pattern = patterns.current();
+    let is_num = event_id == FLP_TIME_SIG_NUM;
+    let (label, valid, range) = if is_num {
+        ("numerator", is_valid_time_sig_num(value), "1-16")
+    } else {
+        ("denominator", is_valid_time_sig_den(value), "2/4/8/16")
+    };
+
+    if !valid {
+        let scope = match pattern {
+            Some(p) => format!("Pattern {} time signature", p),
+            None => "Time signature".to_string(),
+        };
+        let message = format!(
+            "{} {} {} out of sane range ({}) — ignoring",
+            scope, label, value, range
+        );
+        meta.warnings
+            .push(ParseWarning::new(WarningCode::TimeSigOutOfRange, message).at(offset, event_id));
+        return;
     }
-    if &bytes[0..4] != b"FLhd" {
-        return Err(ParseError::InvalidMagic);
+
+    match (pattern.is_some(), is_num) {
+        (true, true) => patterns.set_time_sig_num(value),
+        (true, false) => patterns.set_time_sig_den(value),
+        (false, true) => meta.time_sig_num = Some(value),
+        (false, false) => meta.time_sig_den = Some(value),
     }
+}
 
-    let mut cursor = Cursor::new(bytes);
-    cursor.set_position(4);
+/// Header format value for a regular project file.
+const FORMAT_PROJECT: u16 = 0;
 
-    // Header chunk size (always 6 for standard FLP)
-    let _header_size = cursor
-        .read_u32::<LittleEndian>()
-        .map_err(|_| ParseError::TruncatedHeader)?;
+/// Name of a known non-project header format (score, preset and state files
+/// share the FLP container).
+fn describe_format(format: u16) -> Option<&'static str> {
+    match format {
+        0x10 => Some("score"),
+        0x18 => Some("automation clip"),
+        0x20 => Some("channel state"),
+        0x30 => Some("plugin state"),
+        0x31 => Some("generator state"),
+        0x32 => Some("effect state"),
+        0x40 => Some("mixer insert state"),
+        _ => None,
+    }
+}
 
-    // Format version (u16 LE)
-    let _format = cursor
-        .read_u16::<LittleEndian>()
-        .map_err(|_| ParseError::TruncatedHeader)?;
+/// PPQ values FL Studio offers in project settings.
+const STANDARD_PPQ: [u16; 11] = [24, 48, 72, 96, 120, 144, 168, 192, 384, 768, 960];
 
-    // Number of channels (u16 LE)
-    let channel_count = cursor
-        .read_u16::<LittleEndian>()
-        .map_err(|_| ParseError::TruncatedHeader)?;
+/// Warn about header values that suggest a non-project or damaged file.
+fn validate_header(meta: &mut FlpMetadata) {
+    if meta.format != FORMAT_PROJECT {
+        let warning = match describe_format(meta.format) {
+            Some(kind) => format!(
+                "Header format {:#04x} is a {} file, not a project — metadata may be incomplete",
+                meta.format, kind
+            ),
+            None => format!("Unknown header format {:#04x}", meta.format),
+        };
+        meta.warnings.push(ParseWarning::new(WarningCode::NotAProject, warning));
+    }
+
+    if meta.ppq == 0 {
+        meta.warnings.push(ParseWarning::new(
+            WarningCode::UnusualPpq,
+            "PPQ is 0 — lengths in beats, bars and seconds unavailable",
+        ));
+    } else if !STANDARD_PPQ.contains(&meta.ppq) {
+        meta.warnings.push(ParseWarning::new(
+            WarningCode::UnusualPpq,
+            format!("Unusual PPQ {} — timing values may be off", meta.ppq),
+        ));
+    }
+}
+
+/// Major version from an FL Studio version string such as "21.0.3.3517".
+fn fl_major_version(version: Option<&str>) -> Option<u32> {
+    version?.split('.').next()?.trim().parse().ok()
+}
 
-    // PPQ (u16 LE)
-    let _ppq = cursor
-        .read_u16::<LittleEndian>()
-        .map_err(|_| ParseError::TruncatedHeader)?;
+/// Song length in bars and seconds for a span of ticks, using the project
+/// time signature (4/4 if unset) and tempo.
+fn song_length(
+    ticks: u32,
+    ppq: u16,
+    bpm: Option<f32>,
+    time_sig: (Option<u8>, Option<u8>),
+) -> (Option<f32>, Option<f32>) {
+    if ticks == 0 || ppq == 0 {
+        return (None, None);
+    }
+    let beats = ticks as f32 / ppq as f32;
+    let num = time_sig.0.unwrap_or(4) as f32;
+    let den = time_sig.1.unwrap_or(4) as f32;
+    // Beats are quarter notes; a bar holds `num` notes of length 1/den
+    let quarters_per_bar = num * 4.0 / den;
+    let bars = beats / quarters_per_bar;
+    let secs = bpm.map(|b| beats * 60.0 / b);
+    (Some(bars), secs)
+}
+
+/// Events that only occur in the mixer section. The first one seen ends the
+/// channel section, so plugin names after it belong to effect slots.
+fn is_mixer_event(event_id: u8) -> bool {
+    matches!(
+        event_id,
+        FLP_INSERT_FLAGS
+            | FLP_TEXT_INSERT_NAME
+            | FLP_INSERT_ROUTING
+            | FLP_SLOT_INDEX
+            | FLP_INSERT_OUTPUT
+    )
+}
+
+/// Skips shorter than this are read and discarded so a buffered reader keeps
+/// its buffer; longer ones seek.
+const SEEK_THRESHOLD: u64 = 64 * 1024;
+
+/// Variable-length events whose whole payload is decoded. Plugin state is
+/// read selectively by `plugin::read_state`; for anything else only the
+/// census sample is kept.
+fn is_decoded_event(event_id: u8) -> bool {
+    matches!(
+        event_id,
+        FLP_TEXT_CHAN_NAME
+            | FLP_TEXT_PLUGIN_NAME
+            | FLP_TEXT_PLUGIN_DISPLAY_NAME
+            | FLP_TEXT_PAT_NAME
+            | FLP_PAT_NOTES
+            | FLP_PLAYLIST
+            | FLP_TRACK_DATA
+            | FLP_TEXT_TRACK_NAME
+            | FLP_TEXT_ARRANGEMENT_NAME
+            | FLP_TEXT_SAMPLE_PATH
+            | FLP_AUTOMATION_DATA
+            | FLP_REMOTE_CONTROLLERS
+            | FLP_VERSION
+            | FLP_TEXT_TITLE
+            | FLP_TEXT_ARTISTS
+            | FLP_TEXT_GENRE
+            | FLP_TEXT_URL
+            | FLP_TEXT_COMMENT
+            | FLP_TEXT_COMMENT_RTF
+            | FLP_TIMESTAMP
+            | FLP_TEXT_INSERT_NAME
+            | FLP_INSERT_FLAGS
+            | FLP_INSERT_ROUTING
+            | FLP_MIXER_PARAMS
+    )
+}
+
+/// Move the reader forward `n` bytes.
+fn skip<R: Read + Seek>(reader: &mut R, n: u64) -> io::Result<()> {
+    if n <= SEEK_THRESHOLD {
+        io::copy(&mut reader.by_ref().take(n), &mut io::sink())?;
+    } else {
+        reader.seek(SeekFrom::Current(n as i64))?;
+    }
+    Ok(())
+}
+
+/// Read the first `keep` bytes of a `len`-byte payload and skip the rest.
+fn read_payload<R: Read + Seek>(reader: &mut R, len: u64, keep: u64) -> io::Result<Vec<u8>> {
+    let keep = keep.min(len);
+    let mut payload = Vec::with_capacity(keep as usize);
+    reader.by_ref().take(keep).read_to_end(&mut payload)?;
+    if (payload.len() as u64) < keep {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    skip(reader, len - keep)?;
+    Ok(payload)
+}
+
+/// Parse an FLP file from raw bytes.
+/// Returns FlpMetadata on success or ParseError for fatal errors.
+/// For partial/truncated streams, returns Ok with warnings rather than Err.
+pub fn parse_flp(bytes: &[u8]) -> Result<FlpMetadata, ParseError> {
+    parse_flp_reader(Cursor::new(bytes))
+}
+
+/// Parse an FLP file from a seekable stream positioned at its start.
+/// Payloads the parser does not decode are skipped rather than read, so memory
+/// use is bounded by the largest decoded event instead of the file size.
+pub fn parse_flp_reader<R: Read + Seek>(mut reader: R) -> Result<FlpMetadata, ParseError> {
+    let base = reader.stream_position()?;
+    let stream_end = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(base))?;
+
+    let FlpHeader { format, channel_count, ppq } = read_header(&mut reader)?;
+
+    let mut meta = FlpMetadata {
+        channel_count,
+        format,
+        ppq,
+        ..Default::default()
+    };
+    validate_header(&mut meta);
 
     // Find the FLdt chunk
     // After the header chunk we expect: "FLdt" + 4-byte size + events
     let mut magic = [0u8; 4];
-    if cursor.read_exact(&mut magic).is_err() {
+    if reader.read_exact(&mut magic).is_err() {
         // No event data at all — return header-only metadata
-        return Ok(FlpMetadata {
-            channel_count,
-            ..Default::default()
-        });
+        return Ok(meta);
     }
 
     if &magic != b"FLdt" {
         // Unrecognized chunk — return header-only metadata with warning
-        let mut meta = FlpMetadata {
-            channel_count,
-            ..Default::default()
-        };
-        meta.warnings.push("FLdt chunk not found".to_string());
+        meta.warnings
+            .push(ParseWarning::new(WarningCode::MissingData, "FLdt chunk not found"));
         return Ok(meta);
     }
 
-    let _data_size = cursor.read_u32::<LittleEndian>().unwrap_or(0);
-
-    // State for building channel info
-    let mut meta = FlpMetadata {
-        channel_count,
-        ..Default::default()
-    };
+    let _data_size = reader.read_u32::<LittleEndian>().unwrap_or(0);
 
     let mut legacy_bpm: Option<f32> = None;
     let mut modern_bpm: Option<f32> = None;
 
-    let mut current_name: Option<String> = None;
-    let mut current_plugin: Option<String> = None;
-    let mut current_chan_type: u8 = 0;
-    let mut in_channel = false;
-
-    // Flush the current in-progress channel into generators list
-    let flush_channel =
-        |name: &mut Option<String>,
-         plugin: &mut Option<String>,
-         chan_type: &mut u8,
-         in_ch: &mut bool,
-         generators: &mut Vec<ChannelInfo>| {
-            if *in_ch || name.is_some() {
-                let info = ChannelInfo {
-                    name: name.take().unwrap_or_default(),
-                    plugin_name: plugin.take(),
-                    channel_type: *chan_type,
-                };
-                generators.push(info);
-                *chan_type = 0;
-                *in_ch = false;
-            }
-        };
+    // Plain comments are preferred; newer versions only write the RTF form
+    let mut rtf_comments: Option<String> = None;
+
+    let mut channels = ChannelBuilder::default();
+
+    // Time signature events seen while a pattern is open are per-pattern overrides
+    let mut patterns = PatternBuilder::default();
+
+    let mut mixer = MixerBuilder::default();
+    let mut in_mixer = false;
+
+    let mut arrangements = ArrangementBuilder::default();
+
+    let mut census = EventCensus::default();
 
     loop {
-        // Read event ID
-        let event_id = match cursor.read_u8() {
-            Ok(id) => id,
-            Err(_) => break, // end of stream
+        let offset = reader.stream_position()? - base;
+        let event_id = match read_event_id(&mut reader) {
+            Ok(Some(id)) => id,
+            _ => break, // end of stream
         };
 
-        match event_id {
+        if !in_mixer && is_mixer_event(event_id) {
+            channels.close();
+            in_mixer = true;
+            patterns.close();
+        }
+
+        let kind = EventKind::of(event_id);
+        let len = match read_event_len(&mut reader, event_id) {
+            Ok(header) => header.len,
+            Err(_) => {
+                let message = format!(
+                    "Truncated varint at TEXT event {} — partial data returned",
+                    event_id
+                );
+                meta.warnings.push(
+                    ParseWarning::new(WarningCode::Truncated, message).at(offset, event_id),
+                );
+                break;
+            }
+        };
+        // Checked up front so a corrupt length never drives an allocation
+        let start = reader.stream_position()?;
+        let payload = if len > stream_end.saturating_sub(start) {
+            Err(io::ErrorKind::UnexpectedEof.into())
+        } else if event_id == FLP_PLUGIN_STATE {
+            plugin::read_state(&mut reader, len)
+        } else if kind != EventKind::Data || is_decoded_event(event_id) {
+            read_payload(&mut reader, len, len)
+        } else {
+            read_payload(&mut reader, len, census::SAMPLE_LEN as u64)
+        };
+        let payload = match payload {
+            Ok(p) => p,
+            Err(_) => {
+                let message = match kind {
+                    EventKind::Data => format!(
+                        "Truncated payload at TEXT event {} — partial data returned",
+                        event_id
+                    ),
+                    _ => format!(
+                        "Truncated at {} event {} — partial data returned",
+                        kind.label(),
+                        event_id
+                    ),
+                };
+                meta.warnings.push(
+                    ParseWarning::new(WarningCode::Truncated, message).at(offset, event_id),
+                );
+                break;
+            }
+        };
+
+        // Fixed-size payloads are exactly `len` bytes long here
+        match kind {
             // BYTE events (0-63): 1 byte value
-            0..=63 => {
-                let value = match cursor.read_u8() {
-                    Ok(v) => v,
-                    Err(_) => {
-                        meta.warnings.push(format!(
-                            "Truncated at BYTE event {} — partial data returned",
-                            event_id
-                        ));
-                        break;
+            EventKind::Byte => {
+                let value = payload[0];
+                match event_id {
+                    x if x == FLP_CHAN_TYPE => {
+                        channels.set_type(value);
                     }
-                };
-                if event_id == FLP_CHAN_TYPE {
-                    current_chan_type = value;
+                    x if x == FLP_CHAN_ROUTED_TO => {
+                        channels.set_insert(value);
+                    }
+                    x if x == FLP_TIME_SIG_NUM || x == FLP_TIME_SIG_BEAT => {
+                        apply_time_sig(&mut meta, &mut patterns, offset, event_id, value);
+                    }
+                    _ => census.record(event_id, 1, &[value]),
                 }
             }
 
             // WORD events (64-127): 2 bytes LE
-            64..=127 => {
-                let value = match cursor.read_u16::<LittleEndian>() {
-                    Ok(v) => v,
-                    Err(_) => {
-                        meta.warnings.push(format!(
-                            "Truncated at WORD event {} — partial data returned",
-                            event_id
-                        ));
-                        break;
-                    }
-                };
+            EventKind::Word => {
+                let value = u16::from_le_bytes([payload[0], payload[1]]);
                 match event_id {
                     x if x == FLP_NEW_CHAN => {
-                        flush_channel(
-                            &mut current_name,
-                            &mut current_plugin,
-                            &mut current_chan_type,
-                            &mut in_channel,
-                            &mut meta.generators,
-                        );
-                        in_channel = true;
+                        channels.open(value);
+                        in_mixer = false;
+                        patterns.close();
                     }
                     x if x == FLP_NEW_PAT => {
-                        meta.pattern_count += 1;
+                        patterns.open(value);
                     }
                     x if x == FLP_TEMPO_LEGACY => {
                         let bpm = value as f32;
                         if bpm < 1.0 || bpm > 999.0 {
-                            meta.warnings.push(format!(
-                                "Legacy BPM {} out of sane range (1-999) — ignoring",
-                                bpm
-                            ));
+                            let message =
+                                format!("Legacy BPM {} out of sane range (1-999) — ignoring", bpm);
+                            meta.warnings.push(
+                                ParseWarning::new(WarningCode::TempoOutOfRange, message)
+                                    .at(offset, event_id),
+                            );
                         } else {
                             legacy_bpm = Some(bpm);
                         }
                     }
-                    _ => {} // skip unknown WORD events
+                    x if x == FLP_SLOT_INDEX => {
+                        mixer.end_slot(value);
+                    }
+                    x if x == FLP_NEW_ARRANGEMENT => {
+                        patterns.close();
+                        arrangements.open(value);
+                    }
+                    _ => census.record(event_id, 2, &payload),
                 }
             }
 
             // DWORD events (128-191): 4 bytes LE
-            128..=191 => {
-                let value = match cursor.read_u32::<LittleEndian>() {
-                    Ok(v) => v,
-                    Err(_) => {
-                        meta.warnings.push(format!(
-                            "Truncated at DWORD event {} — partial data returned",
-                            event_id
-                        ));
-                        break;
+            EventKind::Dword => {
+                let value = u32::from_le_bytes([payload[0], payload[1], payload[2], payload[3]]);
+                match event_id {
+                    x if x == FLP_TEMPO => {
+                        let bpm = value as f32 / 1000.0;
+                        if bpm < 1.0 || bpm > 999.0 {
+                            let message =
+                                format!("Modern BPM {} out of sane range (1-999) — ignoring", bpm);
+                            meta.warnings.push(
+                                ParseWarning::new(WarningCode::TempoOutOfRange, message)
+                                    .at(offset, event_id),
+                            );
+                        } else {
+                            modern_bpm = Some(bpm);
+                        }
                     }
-                };
-                if event_id == FLP_TEMPO {
-                    let bpm = value as f32 / 1000.0;
-                    if bpm < 1.0 || bpm > 999.0 {
-                        meta.warnings.push(format!(
-                            "Modern BPM {} out of sane range (1-999) — ignoring",
-                            bpm
-                        ));
-                    } else {
-                        modern_bpm = Some(bpm);
+                    x if x == FLP_INSERT_OUTPUT => {
+                        mixer.end_insert();
+                    }
+                    x if x == FLP_PAT_COLOR => {
+                        patterns.set_color(value);
                     }
+                    _ => census.record(event_id, 4, &payload),
                 }
-                // skip all other DWORD events
             }
 
             // TEXT/VARIABLE events (192-255): varint length + payload bytes
-            192..=255 => {
-                let len = match read_varint(&mut cursor) {
-                    Ok(l) => l as usize,
-                    Err(_) => {
-                        meta.warnings.push(format!(
-                            "Truncated varint at TEXT event {} — partial data returned",
-                            event_id
-                        ));
-                        break;
-                    }
-                };
-                let mut payload = vec![0u8; len];
-                if cursor.read_exact(&mut payload).is_err() {
-                    meta.warnings.push(format!(
-                        "Truncated payload at TEXT event {} — partial data returned",
-                        event_id
-                    ));
-                    break;
-                }
-                let text = decode_string(&payload);
+            EventKind::Data => {
                 match event_id {
                     x if x == FLP_TEXT_CHAN_NAME => {
-                        current_name = Some(text);
+                        channels.set_name(decode_string(&payload));
                     }
-                    x if x == FLP_TEXT_PLUGIN_NAME => {
-                        current_plugin = Some(text);
+                    x if x == FLP_TEXT_PLUGIN_NAME
+                        || x == FLP_TEXT_PLUGIN_DISPLAY_NAME
+                        || x == FLP_PLUGIN_STATE =>
+                    {
+                        let plugin = if in_mixer {
+                            mixer.plugin_mut()
+                        } else {
+                            channels.plugin_mut()
+                        };
+                        match event_id {
+                            x if x == FLP_TEXT_PLUGIN_NAME => {
+                                plugin.set_internal_name(decode_string(&payload))
+                            }
+                            x if x == FLP_TEXT_PLUGIN_DISPLAY_NAME => {
+                                plugin.set_display_name(decode_string(&payload))
+                            }
+                            _ => plugin.set_state(&payload),
+                        }
+                    }
+                    x if x == FLP_TEXT_PAT_NAME => {
+                        patterns.set_name(decode_string(&payload));
+                    }
+                    x if x == FLP_PAT_NOTES => {
+                        patterns.add_notes(&payload);
+                    }
+                    x if x == FLP_PLAYLIST => {
+                        patterns.close();
+                        let fl_major = fl_major_version(meta.fl_version.as_deref());
+                        if !arrangements.add_playlist(&payload, fl_major) {
+                            let message = format!(
+                                "Playlist event has unexpected size {} — ignoring",
+                                payload.len()
+                            );
+                            meta.warnings.push(
+                                ParseWarning::new(WarningCode::MalformedEvent, message)
+                                    .at(offset, event_id),
+                            );
+                        }
+                    }
+                    x if x == FLP_TRACK_DATA => {
+                        arrangements.set_track(&payload);
+                    }
+                    x if x == FLP_TEXT_TRACK_NAME => {
+                        arrangements.set_track_name(decode_string(&payload));
+                    }
+                    x if x == FLP_TEXT_ARRANGEMENT_NAME => {
+                        arrangements.set_name(decode_string(&payload));
+                    }
+                    x if x == FLP_TEXT_SAMPLE_PATH => {
+                        channels.add_sample_path(decode_string(&payload));
+                    }
+                    x if x == FLP_AUTOMATION_DATA => {
+                        channels.set_automation(&payload);
+                    }
+                    x if x == FLP_REMOTE_CONTROLLERS => {
+                        channels.add_remote_controllers(&payload);
                     }
                     x if x == FLP_VERSION => {
-                        meta.fl_version = Some(text);
+                        meta.fl_version = Some(decode_string(&payload));
+                    }
+                    x if x == FLP_TEXT_TITLE => {
+                        meta.title = decode_info_text(&payload);
+                    }
+                    x if x == FLP_TEXT_ARTISTS => {
+                        meta.artist = decode_info_text(&payload);
+                    }
+                    x if x == FLP_TEXT_GENRE => {
+                        meta.genre = decode_info_text(&payload);
                     }
-                    _ => {} // skip unknown TEXT events
+                    x if x == FLP_TEXT_URL => {
+                        meta.url = decode_info_text(&payload);
+                    }
+                    x if x == FLP_TEXT_COMMENT => {
+                        meta.comments = decode_info_text(&payload);
+                    }
+                    x if x == FLP_TEXT_COMMENT_RTF => {
+                        rtf_comments = decode_info_text(&payload).map(|rtf| rtf_to_plain(&rtf));
+                    }
+                    x if x == FLP_TIMESTAMP => {
+                        let (created_at, time_spent) = decode_timestamp(&payload);
+                        meta.created_at = created_at;
+                        meta.time_spent = time_spent;
+                        if created_at.is_none() || time_spent.is_none() {
+                            meta.warnings.push(
+                                ParseWarning::new(
+                                    WarningCode::MalformedEvent,
+                                    "Project timestamp event is malformed — ignoring",
+                                )
+                                .at(offset, event_id),
+                            );
+                        }
+                    }
+                    x if x == FLP_TEXT_INSERT_NAME => {
+                        mixer.set_name(decode_string(&payload));
+                    }
+                    x if x == FLP_INSERT_FLAGS => {
+                        mixer.set_flags(&payload);
+                    }
+                    x if x == FLP_INSERT_ROUTING => {
+                        mixer.set_routing(&payload);
+                    }
+                    x if x == FLP_MIXER_PARAMS => {
+                        mixer.set_params(&payload);
+                    }
+                    _ => census.record(event_id, len.min(u32::MAX as u64) as u32, &payload),
                 }
             }
         }
     }
 
-    // Flush last in-progress channel
-    flush_channel(
-        &mut current_name,
-        &mut current_plugin,
-        &mut current_chan_type,
-        &mut in_channel,
-        &mut meta.generators,
-    );
+    meta.unknown_events = census.finish();
+
+    let (generators, automation) = channels.finish();
+    meta.generators = generators;
+    meta.automation = automation;
+
+    if meta.comments.is_none() {
+        meta.comments = rtf_comments.filter(|c| !c.is_empty());
+    }
+
+    meta.patterns = patterns.finish();
+    meta.pattern_count = meta.patterns.len() as u16;
+
+    meta.mixer = mixer.finish();
+    meta.mixer_track_count = meta.mixer.len() as u16;
+    meta.routing = routing_graph(&meta.generators, &meta.mixer);
+    meta.effects = meta
+        .mixer
+        .iter()
+        .flat_map(|insert| insert.slots.iter())
+        .filter_map(|slot| match &slot.plugin {
+            Some(identity) => Some(identity.internal_name.clone()),
+            None => slot.plugin_name.clone(),
+        })
+        .collect();
 
     // Resolve BPM: modern takes priority over legacy
     meta.bpm = modern_bpm.or(legacy_bpm);
 
     if meta.bpm.is_none() {
-        meta.warnings.push("No BPM event found in file".to_string());
+        meta.warnings
+            .push(ParseWarning::new(WarningCode::MissingBpm, "No BPM event found in file"));
     }
 
+    meta.arrangements = arrangements.finish();
+
+    // All tick-based lengths use this file's own PPQ
+    let ticks_to_beats = |ticks: u32| (ppq > 0).then(|| ticks as f32 / ppq as f32);
+    for pattern in &mut meta.patterns {
+        pattern.length_beats = ticks_to_beats(pattern.length_ticks);
+    }
+    for arrangement in &mut meta.arrangements {
+        arrangement.length_beats = ticks_to_beats(arrangement.length_ticks);
+    }
+
+    let longest = meta.arrangements.iter().map(|a| a.length_ticks).max().unwrap_or(0);
+    let (bars, secs) = song_length(
+        longest,
+        ppq,
+        meta.bpm,
+        (meta.time_sig_num, meta.time_sig_den),
+    );
+    meta.song_length_bars = bars;
+    meta.song_length_secs = secs;
+
     Ok(meta)
 }
 
@@ -301,6 +650,7 @@ pub fn parse_flp(bytes: &[u8]) -> Result<FlpMetadata, ParseError> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::parser::types::{AutomationTarget, ChannelType, ClipKind};
 
     // Build a minimal valid FLP header: "FLhd" + size(6) + format(0) + channels + ppq
     fn make_header(channels: u16, ppq: u16) -> Vec<u8> {
@@ -443,7 +793,7 @@ mod tests {
         let meta = parse_flp(&data).expect("should parse");
         assert!(meta.bpm.is_none(), "No BPM event -> bpm should be None");
         assert!(
-            meta.warnings.iter().any(|w| w.contains("No BPM")),
+            meta.warnings.iter().any(|w| w.message.contains("No BPM")),
             "Should warn about missing BPM"
         );
     }
@@ -458,7 +808,7 @@ mod tests {
         assert!(meta.bpm.is_none(), "Out-of-range BPM should be None");
         // Should have the out-of-range warning AND the no-BPM warning
         assert!(
-            meta.warnings.iter().any(|w| w.contains("out of sane range")),
+            meta.warnings.iter().any(|w| w.message.contains("out of sane range")),
             "Should warn about out-of-range BPM"
         );
     }
@@ -496,6 +846,48 @@ mod tests {
         assert_eq!(meta.pattern_count, 3);
     }
 
+    #[test]
+    fn test_pattern_written_twice_counted_once() {
+        let mut events = Vec::new();
+        events.extend(word_event(FLP_NEW_PAT, 1));
+        events.extend(word_event(FLP_NEW_PAT, 2));
+        events.extend(word_event(FLP_NEW_PAT, 1));
+        let data = build_flp(1, 96, &events);
+        let meta = parse_flp(&data).expect("should parse");
+        assert_eq!(meta.pattern_count, 2);
+    }
+
+    #[test]
+    fn test_pattern_name_color_and_notes() {
+        // Two notes on channel 0, one on channel 1; the last ends at tick 192
+        let mut notes = Vec::new();
+        for (position, channel) in [(0u32, 0u16), (48, 0), (96, 1)] {
+            notes.extend_from_slice(&position.to_le_bytes());
+            notes.extend_from_slice(&0u16.to_le_bytes());
+            notes.extend_from_slice(&channel.to_le_bytes());
+            notes.extend_from_slice(&96u32.to_le_bytes());
+            notes.extend_from_slice(&[0u8; 12]);
+        }
+
+        let mut events = Vec::new();
+        events.extend(word_event(FLP_NEW_PAT, 1));
+        events.extend(raw_text_event(FLP_PAT_NOTES, &notes));
+        events.extend(word_event(FLP_NEW_PAT, 1));
+        events.extend(text_event(FLP_TEXT_PAT_NAME, "Chorus Drums"));
+        events.extend(dword_event(FLP_PAT_COLOR, 0x00FF_8000));
+        let data = build_flp(2, 96, &events);
+        let meta = parse_flp(&data).expect("should parse");
+
+        assert_eq!(meta.patterns.len(), 1);
+        let p = &meta.patterns[0];
+        assert_eq!(p.name.as_deref(), Some("Chorus Drums"));
+        assert_eq!(p.color.as_deref(), Some("#0080ff"));
+        assert_eq!(p.note_count, 3);
+        assert_eq!(p.length_ticks, 192);
+        assert_eq!(p.notes_per_channel.len(), 2);
+        assert_eq!(p.notes_per_channel[0].notes, 2);
+    }
+
     #[test]
     fn test_unknown_event_ids_skipped() {
         let mut events = Vec::new();
@@ -529,11 +921,15 @@ mod tests {
         let meta = parse_flp(&data).expect("truncated file should return partial result, not Err");
         // BPM will be None since event was truncated
         assert!(meta.bpm.is_none());
-        // Should have a truncation warning
-        assert!(
-            meta.warnings.iter().any(|w| w.contains("Truncated")),
-            "Should warn about truncation"
-        );
+        // Should have a truncation warning pointing at the event
+        let warning = meta
+            .warnings
+            .iter()
+            .find(|w| w.code == WarningCode::Truncated)
+            .expect("Should warn about truncation");
+        assert_eq!(warning.severity, crate::parser::types::Severity::Error);
+        assert_eq!(warning.event_id, Some(FLP_TEMPO));
+        assert_eq!(warning.offset, Some(22)); // 14-byte header + 8-byte FLdt prefix
     }
 
     #[test]
@@ -557,7 +953,747 @@ mod tests {
         let data = build_flp(1, 96, &events);
         let meta = parse_flp(&data).expect("should parse");
         assert_eq!(meta.generators.len(), 1);
-        assert_eq!(meta.generators[0].channel_type, 3);
+        assert_eq!(meta.generators[0].channel_type, ChannelType::Layer);
         assert_eq!(meta.generators[0].name, "Snare");
     }
+
+    #[test]
+    fn test_project_time_signature() {
+        let mut events = Vec::new();
+        events.extend(byte_event(FLP_TIME_SIG_NUM, 3));
+        events.extend(byte_event(FLP_TIME_SIG_BEAT, 4));
+        let data = build_flp(1, 96, &events);
+        let meta = parse_flp(&data).expect("should parse");
+        assert_eq!(meta.time_sig_num, Some(3));
+        assert_eq!(meta.time_sig_den, Some(4));
+        assert!(meta.patterns.is_empty());
+    }
+
+    #[test]
+    fn test_time_signature_out_of_range_produces_warning() {
+        let mut events = Vec::new();
+        events.extend(byte_event(FLP_TIME_SIG_NUM, 0));
+        events.extend(byte_event(FLP_TIME_SIG_BEAT, 3));
+        let data = build_flp(1, 96, &events);
+        let meta = parse_flp(&data).expect("should parse");
+        assert!(meta.time_sig_num.is_none());
+        assert!(meta.time_sig_den.is_none());
+        assert_eq!(
+            meta.warnings
+                .iter()
+                .filter(|w| w.code == WarningCode::TimeSigOutOfRange
+                    && w.message.starts_with("Time signature"))
+                .count(),
+            2,
+            "Both components should warn"
+        );
+    }
+
+    #[test]
+    fn test_pattern_time_signature_override() {
+        let mut events = Vec::new();
+        events.extend(byte_event(FLP_TIME_SIG_NUM, 4));
+        events.extend(byte_event(FLP_TIME_SIG_BEAT, 4));
+        events.extend(word_event(FLP_NEW_PAT, 2));
+        events.extend(byte_event(FLP_TIME_SIG_NUM, 7));
+        events.extend(byte_event(FLP_TIME_SIG_BEAT, 8));
+        let data = build_flp(1, 96, &events);
+        let meta = parse_flp(&data).expect("should parse");
+        // Project default is untouched by the pattern override
+        assert_eq!(meta.time_sig_num, Some(4));
+        assert_eq!(meta.time_sig_den, Some(4));
+        assert_eq!(meta.patterns.len(), 1);
+        assert_eq!(meta.patterns[0].index, 2);
+        assert_eq!(meta.patterns[0].time_sig_num, Some(7));
+        assert_eq!(meta.patterns[0].time_sig_den, Some(8));
+    }
+
+    #[test]
+    fn test_new_channel_closes_pattern_scope() {
+        let mut events = Vec::new();
+        events.extend(word_event(FLP_NEW_PAT, 1));
+        events.extend(word_event(FLP_NEW_CHAN, 0));
+        events.extend(byte_event(FLP_TIME_SIG_NUM, 6));
+        events.extend(byte_event(FLP_TIME_SIG_BEAT, 8));
+        let data = build_flp(1, 96, &events);
+        let meta = parse_flp(&data).expect("should parse");
+        assert_eq!(meta.time_sig_num, Some(6));
+        assert_eq!(meta.time_sig_den, Some(8));
+        assert!(meta.patterns[0].time_sig_num.is_none());
+    }
+
+    // Encode insert flags payload: reserved, flags, reserved
+    fn insert_flags_event(enabled: bool) -> Vec<u8> {
+        let flags: u32 = if enabled { 1 << 3 } else { 0 };
+        let mut payload = vec![0u8; 4];
+        payload.extend_from_slice(&flags.to_le_bytes());
+        payload.extend_from_slice(&[0u8; 4]);
+        raw_text_event(FLP_INSERT_FLAGS, &payload)
+    }
+
+    #[test]
+    fn test_mixer_inserts_and_effect_slots() {
+        let mut events = Vec::new();
+        // Master: one effect in slot 0
+        events.extend(insert_flags_event(true));
+        events.extend(text_event(FLP_TEXT_PLUGIN_NAME, "Fruity Limiter"));
+        events.extend(word_event(FLP_SLOT_INDEX, 0));
+        events.extend(word_event(FLP_SLOT_INDEX, 1)); // empty slot
+        events.extend(dword_event(FLP_INSERT_OUTPUT, 0xFFFF_FFFF));
+        // Insert 1: named, muted, effect in slot 2
+        events.extend(insert_flags_event(false));
+        events.extend(text_event(FLP_TEXT_INSERT_NAME, "Drum Bus"));
+        events.extend(text_event(FLP_TEXT_PLUGIN_NAME, "Fruity Compressor"));
+        events.extend(word_event(FLP_SLOT_INDEX, 2));
+        events.extend(dword_event(FLP_INSERT_OUTPUT, 0));
+
+        let data = build_flp(0, 96, &events);
+        let meta = parse_flp(&data).expect("should parse");
+
+        assert_eq!(meta.mixer_track_count, 2);
+        assert_eq!(meta.mixer[0].index, 0);
+        assert!(meta.mixer[0].enabled);
+        assert!(meta.mixer[0].name.is_none());
+        assert_eq!(meta.mixer[0].slots.len(), 1);
+        assert_eq!(meta.mixer[1].name.as_deref(), Some("Drum Bus"));
+        assert!(!meta.mixer[1].enabled);
+        assert_eq!(meta.mixer[1].slots[0].index, 2);
+        assert_eq!(meta.mixer[1].slots[0].plugin_name.as_deref(), Some("Fruity Compressor"));
+        assert_eq!(meta.effects, vec!["Fruity Limiter", "Fruity Compressor"]);
+    }
+
+    #[test]
+    fn test_channel_and_insert_routing() {
+        let mut events = Vec::new();
+        events.extend(word_event(FLP_NEW_CHAN, 0));
+        events.extend(text_event(FLP_TEXT_CHAN_NAME, "Kick"));
+        events.extend(byte_event(FLP_CHAN_ROUTED_TO, 2));
+        events.extend(word_event(FLP_NEW_CHAN, 1));
+        events.extend(text_event(FLP_TEXT_CHAN_NAME, "Unrouted"));
+        // Master, Insert 1, and Insert 2 "Drum Bus" sending to Insert 1 and the master
+        events.extend(insert_flags_event(true));
+        events.extend(dword_event(FLP_INSERT_OUTPUT, 0));
+        events.extend(insert_flags_event(true));
+        events.extend(raw_text_event(FLP_INSERT_ROUTING, &[1, 0, 0]));
+        events.extend(dword_event(FLP_INSERT_OUTPUT, 0));
+        events.extend(insert_flags_event(true));
+        events.extend(text_event(FLP_TEXT_INSERT_NAME, "Drum Bus"));
+        events.extend(raw_text_event(FLP_INSERT_ROUTING, &[1, 1, 0]));
+        events.extend(dword_event(FLP_INSERT_OUTPUT, 0));
+
+        let data = build_flp(2, 96, &events);
+        let meta = parse_flp(&data).expect("should parse");
+
+        assert_eq!(meta.generators[0].insert, Some(2));
+        assert_eq!(meta.generators[1].insert, None);
+        assert_eq!(meta.mixer[2].routes_to, vec![0, 1]);
+        assert_eq!(meta.routing.channels.len(), 1);
+        assert_eq!(meta.routing.inserts[1].name.as_deref(), Some("Drum Bus"));
+        assert_eq!(meta.routing.chain(0), vec![2, 0]);
+        assert!(meta.routing.chain(1).is_empty());
+    }
+
+    #[test]
+    fn test_mixer_plugins_not_attached_to_channels() {
+        let mut events = Vec::new();
+        events.extend(word_event(FLP_NEW_CHAN, 0));
+        events.extend(text_event(FLP_TEXT_CHAN_NAME, "Lead"));
+        events.extend(text_event(FLP_TEXT_PLUGIN_NAME, "Sytrus"));
+        events.extend(insert_flags_event(true));
+        events.extend(text_event(FLP_TEXT_PLUGIN_NAME, "Fruity Reeverb 2"));
+        events.extend(word_event(FLP_SLOT_INDEX, 0));
+        events.extend(dword_event(FLP_INSERT_OUTPUT, 0));
+
+        let data = build_flp(1, 96, &events);
+        let meta = parse_flp(&data).expect("should parse");
+
+        assert_eq!(meta.generators.len(), 1);
+        assert_eq!(meta.generators[0].plugin_name.as_deref(), Some("Sytrus"));
+        assert_eq!(meta.effects, vec!["Fruity Reeverb 2"]);
+    }
+
+    #[test]
+    fn test_project_info_extraction() {
+        let mut timestamp = Vec::new();
+        timestamp.extend_from_slice(&44197.5f64.to_le_bytes()); // 2021-01-01 12:00
+        timestamp.extend_from_slice(&0.25f64.to_le_bytes()); // 6 hours of work
+
+        let mut events = Vec::new();
+        events.extend(text_event(FLP_TEXT_TITLE, "Night Drive"));
+        events.extend(text_event(FLP_TEXT_ARTISTS, "wally"));
+        events.extend(text_event(FLP_TEXT_GENRE, "Synthwave"));
+        events.extend(text_event(FLP_TEXT_URL, "https://example.com"));
+        events.extend(text_event(FLP_TEXT_COMMENT, "needs vocals"));
+        events.extend(raw_text_event(FLP_TIMESTAMP, &timestamp));
+        let data = build_flp(1, 96, &events);
+        let meta = parse_flp(&data).expect("should parse");
+
+        assert_eq!(meta.title.as_deref(), Some("Night Drive"));
+        assert_eq!(meta.artist.as_deref(), Some("wally"));
+        assert_eq!(meta.genre.as_deref(), Some("Synthwave"));
+        assert_eq!(meta.url.as_deref(), Some("https://example.com"));
+        assert_eq!(meta.comments.as_deref(), Some("needs vocals"));
+        assert_eq!(
+            meta.created_at.map(|d| d.to_string()).as_deref(),
+            Some("2021-01-01 12:00:00")
+        );
+        assert_eq!(meta.time_spent.map(|d| d.num_hours()), Some(6));
+    }
+
+    #[test]
+    fn test_rtf_comments_used_when_plain_missing() {
+        let mut events = Vec::new();
+        events.extend(text_event(FLP_TEXT_COMMENT_RTF, r"{\rtf1\ansi\pard mix notes\par}"));
+        events.extend(text_event(FLP_TEXT_TITLE, ""));
+        let data = build_flp(1, 96, &events);
+        let meta = parse_flp(&data).expect("should parse");
+        assert_eq!(meta.comments.as_deref(), Some("mix notes"));
+        assert!(meta.title.is_none(), "Empty title should be None");
+    }
+
+    #[test]
+    fn test_malformed_timestamp_produces_warning() {
+        let mut events = Vec::new();
+        events.extend(raw_text_event(FLP_TIMESTAMP, &[1, 2, 3]));
+        let data = build_flp(1, 96, &events);
+        let meta = parse_flp(&data).expect("should parse");
+        assert!(meta.created_at.is_none());
+        assert!(meta.time_spent.is_none());
+        assert!(meta.warnings.iter().any(|w| w.message.contains("timestamp")));
+    }
+
+    #[test]
+    fn test_sample_paths_per_channel() {
+        let mut events = Vec::new();
+        events.extend(word_event(FLP_NEW_CHAN, 0));
+        events.extend(text_event(FLP_TEXT_CHAN_NAME, "Kick"));
+        events.extend(text_event(FLP_TEXT_SAMPLE_PATH, "C:\\Samples\\kick.wav"));
+        events.extend(word_event(FLP_NEW_CHAN, 1));
+        events.extend(text_event(FLP_TEXT_CHAN_NAME, "Synth"));
+        events.extend(text_event(FLP_TEXT_SAMPLE_PATH, ""));
+        let data = build_flp(2, 96, &events);
+        let meta = parse_flp(&data).expect("should parse");
+        assert_eq!(meta.generators[0].sample_paths, vec!["C:\\Samples\\kick.wav"]);
+        assert!(meta.generators[1].sample_paths.is_empty(), "Empty path should be skipped");
+    }
+
+    // Encode one legacy (32-byte) playlist item
+    fn playlist_item(position: u32, item_index: u16, length: u32, track: u16) -> Vec<u8> {
+        let mut v = Vec::new();
+        v.extend_from_slice(&position.to_le_bytes());
+        v.extend_from_slice(&20480u16.to_le_bytes());
+        v.extend_from_slice(&item_index.to_le_bytes());
+        v.extend_from_slice(&length.to_le_bytes());
+        v.extend_from_slice(&(500 - track).to_le_bytes());
+        v.extend_from_slice(&[0u8; 18]);
+        v
+    }
+
+    #[test]
+    fn test_playlist_clips_and_song_length() {
+        // 16 bars of 4/4 at 96 PPQ = 6144 ticks
+        let mut items = Vec::new();
+        items.extend(playlist_item(0, 20480 + 1, 3072, 1)); // pattern 1, track 1
+        items.extend(playlist_item(3072, 2, 3072, 2)); // audio clip on channel 2, track 2
+
+        let mut events = Vec::new();
+        events.extend(dword_event(FLP_TEMPO, 120_000));
+        events.extend(word_event(FLP_NEW_ARRANGEMENT, 0));
+        events.extend(text_event(FLP_TEXT_ARRANGEMENT_NAME, "Arrangement"));
+        events.extend(raw_text_event(FLP_PLAYLIST, &items));
+        events.extend(raw_text_event(FLP_TRACK_DATA, &1u32.to_le_bytes()));
+        events.extend(text_event(FLP_TEXT_TRACK_NAME, "Drums"));
+        let data = build_flp(3, 96, &events);
+        let meta = parse_flp(&data).expect("should parse");
+
+        assert_eq!(meta.arrangements.len(), 1);
+        let arr = &meta.arrangements[0];
+        assert_eq!(arr.name.as_deref(), Some("Arrangement"));
+        assert_eq!(arr.clips.len(), 2);
+        assert_eq!(arr.clips[0].kind, ClipKind::Pattern);
+        assert_eq!(arr.clips[0].index, 1);
+        assert_eq!(arr.clips[0].track, 1);
+        assert_eq!(arr.clips[1].kind, ClipKind::Channel);
+        assert_eq!(arr.clips[1].index, 2);
+        assert_eq!(arr.clips[1].track, 2);
+        assert_eq!(arr.tracks[0].name, "Drums");
+        assert_eq!(arr.length_ticks, 6144);
+
+        assert!((meta.song_length_bars.unwrap() - 16.0).abs() < 0.001);
+        // 64 beats at 120 BPM = 32 seconds
+        assert!((meta.song_length_secs.unwrap() - 32.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_song_length_uses_time_signature() {
+        let items = playlist_item(0, 20480 + 1, 96 * 12, 1); // 12 beats
+        let mut events = Vec::new();
+        events.extend(byte_event(FLP_TIME_SIG_NUM, 3));
+        events.extend(byte_event(FLP_TIME_SIG_BEAT, 4));
+        events.extend(raw_text_event(FLP_PLAYLIST, &items));
+        let data = build_flp(1, 96, &events);
+        let meta = parse_flp(&data).expect("should parse");
+        // Implicit arrangement for pre-FL20 files
+        assert_eq!(meta.arrangements.len(), 1);
+        assert!((meta.song_length_bars.unwrap() - 4.0).abs() < 0.001);
+        assert!(meta.song_length_secs.is_none(), "No BPM -> no duration");
+    }
+
+    #[test]
+    fn test_bad_playlist_size_produces_warning() {
+        let mut events = Vec::new();
+        events.extend(raw_text_event(FLP_PLAYLIST, &[0u8; 33]));
+        let data = build_flp(1, 96, &events);
+        let meta = parse_flp(&data).expect("should parse");
+        assert!(meta.warnings.iter().any(|w| w.message.contains("Playlist")));
+    }
+
+    #[test]
+    fn test_header_format_and_ppq_kept() {
+        let data = build_flp(1, 960, &[]);
+        let meta = parse_flp(&data).expect("should parse");
+        assert_eq!(meta.ppq, 960);
+        assert_eq!(meta.format, 0);
+        assert!(!meta
+            .warnings
+            .iter()
+            .any(|w| matches!(w.code, WarningCode::UnusualPpq | WarningCode::NotAProject)));
+    }
+
+    #[test]
+    fn test_unusual_ppq_and_format_warn() {
+        let mut data = build_flp(1, 100, &[]);
+        data[8..10].copy_from_slice(&0x30u16.to_le_bytes()); // plugin state
+        let meta = parse_flp(&data).expect("should parse");
+        assert!(meta.warnings.iter().any(|w| w.message.contains("Unusual PPQ 100")));
+        assert!(meta.warnings.iter().any(|w| w.message.contains("plugin state")));
+    }
+
+    #[test]
+    fn test_lengths_use_file_ppq() {
+        // One 4-beat note at PPQ 960; the same ticks at 96 would read as 40 beats
+        let mut notes = Vec::new();
+        notes.extend_from_slice(&0u32.to_le_bytes());
+        notes.extend_from_slice(&[0u8; 4]);
+        notes.extend_from_slice(&3840u32.to_le_bytes());
+        notes.extend_from_slice(&[0u8; 12]);
+        let mut item = Vec::new();
+        item.extend_from_slice(&0u32.to_le_bytes());
+        item.extend_from_slice(&20480u16.to_le_bytes());
+        item.extend_from_slice(&20481u16.to_le_bytes());
+        item.extend_from_slice(&(3840u32 * 4).to_le_bytes());
+        item.extend_from_slice(&[0u8; 20]);
+
+        let mut events = Vec::new();
+        events.extend(dword_event(FLP_TEMPO, 120_000));
+        events.extend(word_event(FLP_NEW_PAT, 1));
+        events.extend(raw_text_event(FLP_PAT_NOTES, &notes));
+        events.extend(raw_text_event(FLP_PLAYLIST, &item));
+        let data = build_flp(1, 960, &events);
+        let meta = parse_flp(&data).expect("should parse");
+
+        assert_eq!(meta.patterns[0].length_beats, Some(4.0));
+        assert_eq!(meta.arrangements[0].length_beats, Some(16.0));
+        assert!((meta.song_length_bars.unwrap() - 4.0).abs() < 0.001);
+        assert!((meta.song_length_secs.unwrap() - 8.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_zero_ppq_leaves_lengths_empty() {
+        let item = {
+            let mut v = Vec::new();
+            v.extend_from_slice(&0u32.to_le_bytes());
+            v.extend_from_slice(&20480u16.to_le_bytes());
+            v.extend_from_slice(&20481u16.to_le_bytes());
+            v.extend_from_slice(&384u32.to_le_bytes());
+            v.extend_from_slice(&[0u8; 20]);
+            v
+        };
+        let data = build_flp(1, 0, &raw_text_event(FLP_PLAYLIST, &item));
+        let meta = parse_flp(&data).expect("should parse");
+        assert!(meta.arrangements[0].length_beats.is_none());
+        assert!(meta.song_length_bars.is_none());
+        assert!(meta.warnings.iter().any(|w| w.message.contains("PPQ is 0")));
+    }
+
+    #[test]
+    fn test_renamed_plugin_keeps_identity() {
+        let mut events = Vec::new();
+        events.extend(word_event(FLP_NEW_CHAN, 0));
+        events.extend(text_event(FLP_TEXT_CHAN_NAME, "Lead"));
+        events.extend(text_event(FLP_TEXT_PLUGIN_NAME, "Sytrus"));
+        events.extend(text_event(FLP_TEXT_PLUGIN_DISPLAY_NAME, "My Lead"));
+        // Same display name on a VST effect in the mixer
+        let mut state = 10u32.to_le_bytes().to_vec();
+        state.extend_from_slice(&54u32.to_le_bytes());
+        state.extend_from_slice(&(6u64).to_le_bytes());
+        state.extend_from_slice(b"Pro-Q3");
+        events.extend(insert_flags_event(true));
+        events.extend(text_event(FLP_TEXT_PLUGIN_NAME, "Fruity Wrapper"));
+        events.extend(text_event(FLP_TEXT_PLUGIN_DISPLAY_NAME, "EQ"));
+        events.extend(raw_text_event(FLP_PLUGIN_STATE, &state));
+        events.extend(word_event(FLP_SLOT_INDEX, 0));
+        events.extend(dword_event(FLP_INSERT_OUTPUT, 0));
+
+        let data = build_flp(1, 96, &events);
+        let meta = parse_flp(&data).expect("should parse");
+
+        let lead = &meta.generators[0];
+        assert_eq!(lead.plugin_name.as_deref(), Some("My Lead"));
+        assert_eq!(lead.plugin.as_ref().unwrap().internal_name, "Sytrus");
+
+        let slot = &meta.mixer[0].slots[0];
+        assert_eq!(slot.plugin_name.as_deref(), Some("EQ"));
+        assert_eq!(slot.plugin.as_ref().unwrap().internal_name, "Pro-Q3");
+        assert_eq!(meta.effects, vec!["Pro-Q3"]);
+    }
+
+    #[test]
+    fn test_automation_clip_not_listed_as_generator() {
+        let mut automation = vec![0u8; 17];
+        automation.extend_from_slice(&2u32.to_le_bytes());
+        automation.extend_from_slice(&[0u8; 48]);
+        // Clip on channel 1 drives parameter 0 of channel 0
+        let mut controller = vec![0u8, 0, 1, 0, 0, 0, 0, 0];
+        controller.extend_from_slice(&0u16.to_le_bytes());
+        controller.extend_from_slice(&0i16.to_le_bytes());
+        controller.extend_from_slice(&[0u8; 8]);
+
+        let mut events = Vec::new();
+        events.extend(word_event(FLP_NEW_CHAN, 0));
+        events.extend(byte_event(FLP_CHAN_TYPE, 0));
+        events.extend(text_event(FLP_TEXT_CHAN_NAME, "Kick"));
+        events.extend(word_event(FLP_NEW_CHAN, 1));
+        events.extend(byte_event(FLP_CHAN_TYPE, 5));
+        events.extend(text_event(FLP_TEXT_CHAN_NAME, "Kick volume"));
+        events.extend(raw_text_event(FLP_AUTOMATION_DATA, &automation));
+        events.extend(raw_text_event(FLP_REMOTE_CONTROLLERS, &controller));
+
+        let data = build_flp(2, 96, &events);
+        let meta = parse_flp(&data).expect("should parse");
+        assert_eq!(meta.generators.len(), 1);
+        assert_eq!(meta.generators[0].channel_type, ChannelType::Sampler);
+        assert_eq!(meta.automation.len(), 1);
+        let clip = &meta.automation[0];
+        assert_eq!(clip.name, "Kick volume");
+        assert_eq!(clip.point_count, 2);
+        assert_eq!(
+            clip.target,
+            Some(AutomationTarget::Channel { channel: 0, parameter: 0, plugin_parameter: false })
+        );
+    }
+
+    #[test]
+    fn test_unhandled_events_counted() {
+        let mut events = Vec::new();
+        events.extend(byte_event(30, 1));
+        events.extend(raw_text_event(250, &[0xAB; 20]));
+        events.extend(raw_text_event(250, &[0xCD; 3]));
+        events.extend(dword_event(FLP_TEMPO, 120_000));
+        let data = build_flp(1, 96, &events);
+        let meta = parse_flp(&data).expect("should parse");
+
+        let ids: Vec<u8> = meta.unknown_events.iter().map(|e| e.event_id).collect();
+        assert_eq!(ids, vec![30, 250], "Handled events must not be counted");
+        let e = &meta.unknown_events[1];
+        assert_eq!((e.count, e.min_size, e.max_size), (2, 3, 20));
+        assert_eq!(e.sample[0], 0xAB);
+    }
+
+    /// Counts the bytes actually read, to check that payloads are skipped.
+    struct CountingReader<R> {
+        inner: R,
+        read: u64,
+    }
+
+    impl<R: Read> Read for CountingReader<R> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.inner.read(buf)?;
+            self.read += n as u64;
+            Ok(n)
+        }
+    }
+
+    impl<R: Seek> Seek for CountingReader<R> {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    #[test]
+    fn test_reader_skips_large_payloads() {
+        let mut events = Vec::new();
+        events.extend(raw_text_event(250, &vec![0xEE; 1_000_000]));
+        events.extend(dword_event(FLP_TEMPO, 140_000));
+        let data = build_flp(1, 96, &events);
+
+        let mut reader = CountingReader { inner: Cursor::new(&data), read: 0 };
+        let meta = parse_flp_reader(&mut reader).expect("should parse");
+        assert_eq!(meta.bpm, Some(140.0));
+        assert!(reader.read < 1024, "Unknown payload should be skipped, read {}", reader.read);
+        assert_eq!(meta.unknown_events[0].max_size, 1_000_000);
+        assert_eq!(meta.unknown_events[0].sample.len(), census::SAMPLE_LEN);
+    }
+
+    #[test]
+    fn test_truncated_skipped_payload_warns() {
+        let mut events = dword_event(FLP_TEMPO, 140_000);
+        let mut cut = raw_text_event(250, &vec![0u8; 100_000]);
+        cut.truncate(50);
+        events.extend(cut);
+        let data = build_flp(1, 96, &events);
+        let meta = parse_flp(&data).expect("should parse");
+        assert_eq!(meta.bpm, Some(140.0));
+        let warning = meta
+            .warnings
+            .iter()
+            .find(|w| w.code == WarningCode::Truncated)
+            .expect("Should warn about truncation");
+        assert_eq!(warning.event_id, Some(250));
+        assert!(meta.unknown_events.is_empty());
+    }
+
+    #[test]
+    fn test_event_value_with_short_payload() {
+        let event = |kind, payload: &[u8]| Event {
+            id: 0,
+            kind,
+            offset: 0,
+            payload: payload.to_vec(),
+        };
+        assert_eq!(event(EventKind::Byte, &[]).value(), None);
+        assert_eq!(event(EventKind::Word, &[1]).value(), None);
+        assert_eq!(event(EventKind::Dword, &[1, 2, 3]).value(), None);
+        assert_eq!(event(EventKind::Word, &[1, 2, 3]).value(), Some(0x0201));
+    }
+
+    #[test]
+    fn test_event_reader_is_lossless() {
+        let mut events = Vec::new();
+        events.extend(byte_event(FLP_CHAN_TYPE, 2));
+        events.extend(word_event(FLP_NEW_CHAN, 7));
+        events.extend(dword_event(FLP_TEMPO, 140_000));
+        events.extend(raw_text_event(250, &[0xAB; 200]));
+        let data = build_flp(1, 96, &events);
+
+        let (header, reader) = read_events(&data[..]).expect("should parse header");
+        assert_eq!(header.ppq, 96);
+        let events: Vec<Event> = reader.collect::<Result<_, _>>().expect("no truncation");
+        let summary: Vec<(u8, EventKind, u64, Option<u32>)> =
+            events.iter().map(|e| (e.id, e.kind, e.offset, e.value())).collect();
+        assert_eq!(
+            summary,
+            vec![
+                (FLP_CHAN_TYPE, EventKind::Byte, 22, Some(2)),
+                (FLP_NEW_CHAN, EventKind::Word, 24, Some(7)),
+                (FLP_TEMPO, EventKind::Dword, 27, Some(140_000)),
+                (250, EventKind::Data, 32, None),
+            ]
+        );
+        assert_eq!(events[3].payload, vec![0xAB; 200]);
+
+        let (_, mut reader) = read_events(&data[..data.len() - 1]).unwrap();
+        assert_eq!(reader.by_ref().filter(|e| e.is_err()).count(), 1);
+        assert!(reader.next().is_none());
+    }
+
+    /// Encode a data event whose varint claims `claimed` bytes but carries
+    /// only `bytes`, as a corrupt file would.
+    fn forged_event(id: u8, claimed: u64, bytes: &[u8]) -> Vec<u8> {
+        let mut v = vec![id];
+        let mut remaining = claimed;
+        loop {
+            let byte = (remaining & 0x7F) as u8;
+            remaining >>= 7;
+            if remaining > 0 {
+                v.push(byte | 0x80);
+            } else {
+                v.push(byte);
+                break;
+            }
+        }
+        v.extend_from_slice(bytes);
+        v
+    }
+
+    #[test]
+    fn test_forged_lengths_do_not_allocate() {
+        // Each of these would abort the process if the length drove a
+        // `vec![0u8; len]`: unknown, decoded and plugin-state payloads
+        for id in [250, FLP_TEXT_TITLE, FLP_PLUGIN_STATE] {
+            let mut events = dword_event(FLP_TEMPO, 140_000);
+            events.extend(forged_event(id, u64::MAX >> 2, &[1, 2, 3]));
+            let data = build_flp(1, 96, &events);
+
+            let meta = parse_flp(&data).expect("should parse");
+            assert_eq!(meta.bpm, Some(140.0));
+            assert!(meta.warnings.iter().any(|w| w.code == WarningCode::Truncated));
+
+            let (_, reader) = read_events(&data[..]).unwrap();
+            let results: Vec<_> = reader.collect();
+            assert!(results.last().unwrap().is_err());
+        }
+    }
+
+    /// Parse every project in `tests/corpus` and compare the metadata with the
+    /// JSON next to it. Run with `UPDATE_SNAPSHOTS=1` to write missing or
+    /// changed snapshots, then review the diff.
+    #[test]
+    fn test_corpus_snapshots() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus");
+        let update = std::env::var_os("UPDATE_SNAPSHOTS").is_some();
+        let mut projects: Vec<_> = std::fs::read_dir(&dir)
+            .expect("corpus directory should exist")
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "flp"))
+            .collect();
+        projects.sort();
+
+        let mut failures = Vec::new();
+        for path in projects {
+            let file = std::fs::File::open(&path).unwrap();
+            let meta = parse_flp_reader(io::BufReader::new(file))
+                .unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+            let actual = serde_json::to_value(&meta).unwrap();
+            let snapshot = path.with_extension("json");
+
+            let expected: Option<serde_json::Value> = std::fs::read_to_string(&snapshot)
+                .ok()
+                .map(|s| serde_json::from_str(&s).expect("snapshot should be valid JSON"));
+            if expected.as_ref() == Some(&actual) {
+                continue;
+            }
+            if update {
+                let json = serde_json::to_string_pretty(&actual).unwrap() + "\n";
+                std::fs::write(&snapshot, json).unwrap();
+            } else {
+                failures.push(path.display().to_string());
+            }
+        }
+        assert!(
+            failures.is_empty(),
+            "Metadata differs from snapshot (UPDATE_SNAPSHOTS=1 to accept): {:?}",
+            failures
+        );
+    }
+
+    mod props {
+        use super::*;
+        use crate::parser::dump::dump_flp;
+        use crate::parser::tree::group_events;
+        use proptest::prelude::*;
+
+        /// IDs the parser decodes, so generated streams hit real code paths
+        /// rather than only the unknown-event census.
+        const KNOWN_IDS: &[u8] = &[
+            FLP_TIME_SIG_NUM,
+            FLP_TIME_SIG_BEAT,
+            FLP_CHAN_TYPE,
+            FLP_CHAN_ROUTED_TO,
+            FLP_NEW_CHAN,
+            FLP_NEW_PAT,
+            FLP_TEMPO_LEGACY,
+            FLP_SLOT_INDEX,
+            FLP_NEW_ARRANGEMENT,
+            FLP_INSERT_OUTPUT,
+            FLP_PAT_COLOR,
+            FLP_TEMPO,
+            FLP_TEXT_CHAN_NAME,
+            FLP_TEXT_PAT_NAME,
+            FLP_TEXT_TITLE,
+            FLP_TEXT_COMMENT,
+            FLP_TEXT_SAMPLE_PATH,
+            FLP_TEXT_COMMENT_RTF,
+            FLP_VERSION,
+            FLP_TEXT_PLUGIN_NAME,
+            FLP_TEXT_PLUGIN_DISPLAY_NAME,
+            FLP_TEXT_INSERT_NAME,
+            FLP_PLUGIN_STATE,
+            FLP_PAT_NOTES,
+            FLP_MIXER_PARAMS,
+            FLP_REMOTE_CONTROLLERS,
+            FLP_PLAYLIST,
+            FLP_AUTOMATION_DATA,
+            FLP_INSERT_ROUTING,
+            FLP_INSERT_FLAGS,
+            FLP_TIMESTAMP,
+            FLP_TRACK_DATA,
+            FLP_TEXT_ARRANGEMENT_NAME,
+        ];
+
+        /// One well-formed event with a random value or payload.
+        fn event() -> impl Strategy<Value = Vec<u8>> {
+            let id = prop_oneof![any::<u8>(), prop::sample::select(KNOWN_IDS)];
+            (id, any::<u32>(), prop::collection::vec(any::<u8>(), 0..96)).prop_map(
+                |(id, value, payload)| match EventKind::of(id) {
+                    EventKind::Byte => byte_event(id, value as u8),
+                    EventKind::Word => word_event(id, value as u16),
+                    EventKind::Dword => dword_event(id, value),
+                    EventKind::Data => raw_text_event(id, &payload),
+                },
+            )
+        }
+
+        fn events() -> impl Strategy<Value = Vec<Vec<u8>>> {
+            prop::collection::vec(event(), 0..64)
+        }
+
+        /// Every consumer of an event stream, none of which may panic.
+        fn parse_all(data: &[u8]) {
+            let _ = parse_flp(data);
+            let _ = dump_flp(data);
+            if let Ok((_, reader)) = read_events(data) {
+                group_events(reader.map_while(Result::ok));
+            }
+        }
+
+        proptest! {
+            #[test]
+            fn well_formed_streams_parse(events in events(), ppq in any::<u16>()) {
+                let data = build_flp(1, ppq, &events.concat());
+                let mut reader = CountingReader { inner: Cursor::new(&data), read: 0 };
+                prop_assert!(parse_flp_reader(&mut reader).is_ok());
+                prop_assert!(reader.read <= data.len() as u64);
+
+                let (_, reader) = read_events(&data[..]).unwrap();
+                let read: Vec<Event> = reader.collect::<Result<_, _>>().unwrap();
+                let ids: Vec<u8> = read.iter().map(|e| e.id).collect();
+                let expected: Vec<u8> = events.iter().map(|e| e[0]).collect();
+                prop_assert_eq!(ids, expected);
+                parse_all(&data);
+            }
+
+            #[test]
+            fn truncated_streams_do_not_panic(
+                events in events(),
+                cut in any::<prop::sample::Index>(),
+            ) {
+                let data = build_flp(1, 96, &events.concat());
+                parse_all(&data[..cut.index(data.len() + 1)]);
+            }
+
+            #[test]
+            fn forged_lengths_do_not_panic(
+                events in events(),
+                id in 192u8..=255,
+                claimed in any::<u64>(),
+                tail in prop::collection::vec(any::<u8>(), 0..32),
+            ) {
+                let mut data = events.concat();
+                data.extend(forged_event(id, claimed, &tail));
+                let data = build_flp(1, 96, &data);
+                prop_assert!(parse_flp(&data).is_ok());
+                parse_all(&data);
+            }
+
+            #[test]
+            fn random_bytes_do_not_panic(body in prop::collection::vec(any::<u8>(), 0..2048)) {
+                let mut data = make_header(1, 96);
+                data.extend_from_slice(b"FLdt");
+                data.extend_from_slice(&(body.len() as u32).to_le_bytes());
+                data.extend(body);
+                parse_all(&data);
+            }
+        }
+    }
 }
@@ -0,0 +1,188 @@
+use super::plugin::PluginBuilder;
+use super::types::{ChannelInfo, ChannelRoute, InsertRoute, MixerInsert, MixerSlot, RoutingGraph};
+
+/// Insert flag bit set while the insert is enabled (unmuted).
+const INSERT_FLAG_ENABLED: u32 = 1 << 3;
+
+/// Mixer parameter record ID for a slot's on/off switch.
+const PARAM_SLOT_ENABLED: u8 = 0;
+
+/// Size of one record in the mixer parameters (225) payload.
+const PARAM_RECORD_SIZE: usize = 12;
+
+/// Accumulates mixer insert/slot events in stream order.
+///
+/// FL Studio writes each insert as: flags, optional name, then for every slot
+/// the plugin events followed by a slot index event, and finally the insert
+/// output event which closes the insert. Slot on/off state lives separately
+/// in the mixer parameters event and is applied in `finish`.
+#[derive(Default)]
+pub(crate) struct MixerBuilder {
+    inserts: Vec<MixerInsert>,
+    current: MixerInsert,
+    pending_plugin: PluginBuilder,
+    slot_states: Vec<(u16, u16, bool)>,
+}
+
+impl MixerBuilder {
+    pub fn set_flags(&mut self, payload: &[u8]) {
+        // Payload layout: u32 reserved, u32 flags, u32 reserved
+        if let Some(bytes) = payload.get(4..8) {
+            let flags = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+            self.current.enabled = flags & INSERT_FLAG_ENABLED != 0;
+        }
+    }
+
+    pub fn set_name(&mut self, name: String) {
+        if !name.is_empty() {
+            self.current.name = Some(name);
+        }
+    }
+
+    /// Decode the insert routing event: one byte per insert, non-zero where
+    /// this insert sends to it.
+    pub fn set_routing(&mut self, payload: &[u8]) {
+        self.current.routes_to = payload
+            .iter()
+            .enumerate()
+            .filter(|(_, &routed)| routed != 0)
+            .map(|(target, _)| target as u16)
+            .collect();
+    }
+
+    /// Plugin events for the slot being read; closed by `end_slot`.
+    pub fn plugin_mut(&mut self) -> &mut PluginBuilder {
+        &mut self.pending_plugin
+    }
+
+    /// Close the current slot. Empty slots carry no plugin and are not recorded.
+    pub fn end_slot(&mut self, index: u16) {
+        if self.pending_plugin.is_empty() {
+            return;
+        }
+        let (display_name, plugin) = self.pending_plugin.take();
+        self.current.slots.push(MixerSlot {
+            index,
+            plugin_name: display_name,
+            plugin,
+            enabled: true,
+        });
+    }
+
+    pub fn end_insert(&mut self) {
+        self.pending_plugin = PluginBuilder::default();
+        let index = self.inserts.len() as u16;
+        let mut insert = std::mem::take(&mut self.current);
+        insert.index = index;
+        self.inserts.push(insert);
+    }
+
+    /// Decode the mixer parameters event, keeping only slot on/off records.
+    pub fn set_params(&mut self, payload: &[u8]) {
+        for record in payload.chunks_exact(PARAM_RECORD_SIZE) {
+            // Record layout: u32 reserved, u8 id, u8 reserved, u16 channel data, i32 value
+            if record[4] != PARAM_SLOT_ENABLED {
+                continue;
+            }
+            let channel_data = u16::from_le_bytes([record[6], record[7]]);
+            let value = i32::from_le_bytes([record[8], record[9], record[10], record[11]]);
+            let insert = (channel_data >> 6) & 0x7F;
+            let slot = channel_data & 0x3F;
+            self.slot_states.push((insert, slot, value != 0));
+        }
+    }
+
+    pub fn finish(self) -> Vec<MixerInsert> {
+        let mut inserts = self.inserts;
+        for (insert, slot, enabled) in self.slot_states {
+            if let Some(s) = inserts
+                .get_mut(insert as usize)
+                .and_then(|i| i.slots.iter_mut().find(|s| s.index == slot))
+            {
+                s.enabled = enabled;
+            }
+        }
+        inserts
+    }
+}
+
+/// Collect channel targets and insert sends into one graph.
+pub(crate) fn routing_graph(channels: &[ChannelInfo], inserts: &[MixerInsert]) -> RoutingGraph {
+    RoutingGraph {
+        channels: channels
+            .iter()
+            .filter_map(|c| {
+                c.insert.map(|insert| ChannelRoute {
+                    channel: c.index,
+                    name: c.name.clone(),
+                    insert,
+                })
+            })
+            .collect(),
+        inserts: inserts
+            .iter()
+            .filter(|i| !i.routes_to.is_empty())
+            .map(|i| InsertRoute {
+                insert: i.index,
+                name: i.name.clone(),
+                targets: i.routes_to.clone(),
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn param_record(id: u8, insert: u16, slot: u16, value: i32) -> Vec<u8> {
+        let mut v = vec![0u8; 4];
+        v.push(id);
+        v.push(0);
+        v.extend_from_slice(&((insert << 6) | slot).to_le_bytes());
+        v.extend_from_slice(&value.to_le_bytes());
+        v
+    }
+
+    #[test]
+    fn test_empty_slots_are_skipped() {
+        let mut b = MixerBuilder::default();
+        b.end_slot(0);
+        b.plugin_mut().set_internal_name("Fruity Limiter".to_string());
+        b.end_slot(1);
+        b.end_insert();
+        let inserts = b.finish();
+        assert_eq!(inserts.len(), 1);
+        assert_eq!(inserts[0].slots.len(), 1);
+        assert_eq!(inserts[0].slots[0].index, 1);
+    }
+
+    #[test]
+    fn test_params_disable_slot() {
+        let mut b = MixerBuilder::default();
+        b.end_insert();
+        b.plugin_mut().set_internal_name("Fruity Reeverb 2".to_string());
+        b.end_slot(3);
+        b.end_insert();
+
+        let mut params = param_record(PARAM_SLOT_ENABLED, 1, 3, 0);
+        // Non-slot records (e.g. volume) are ignored
+        params.extend(param_record(192, 1, 0, 12800));
+        b.set_params(&params);
+
+        let inserts = b.finish();
+        assert!(!inserts[1].slots[0].enabled);
+    }
+
+    #[test]
+    fn test_slot_without_a_name() {
+        let mut b = MixerBuilder::default();
+        // State that is not a VST wrapper's names nothing
+        b.plugin_mut().set_state(&[0u8; 8]);
+        b.end_slot(0);
+        b.end_insert();
+        let inserts = b.finish();
+        assert_eq!(inserts[0].slots.len(), 1);
+        assert!(inserts[0].slots[0].plugin_name.is_none());
+    }
+}
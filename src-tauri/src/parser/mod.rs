@@ -1,6 +1,22 @@
+mod arrangement;
+mod census;
+mod channel;
+pub mod dump;
 pub mod events;
 pub mod flp;
+mod mixer;
+mod pattern;
+mod plugin;
+mod project;
+pub mod tree;
 pub mod types;
 
-pub use flp::{parse_flp, ParseError};
-pub use types::{ChannelInfo, FlpMetadata};
+/// Bump whenever the parser starts extracting something new, so projects
+/// stored by an older parser are read again on the next scan.
+pub const PARSER_VERSION: i64 = 1;
+
+pub use events::{read_events, Event, EventKind, EventReader, FlpHeader};
+pub use flp::{parse_flp, parse_flp_reader, ParseError};
+pub use plugin::WRAPPER_INTERNAL_NAME;
+pub use tree::{group_events, Scope, ScopeKind};
+pub use types::{Arrangement, AutomationClip, ChannelInfo, ChannelType, FlpMetadata, MixerInsert, MixerSlot, PatternInfo, PluginIdentity, ParseWarning, RoutingGraph, Severity, WarningCode};
@@ -0,0 +1,152 @@
+use std::collections::BTreeMap;
+
+use super::types::{PatternChannelNotes, PatternInfo};
+
+/// Size of one note record in the pattern notes (224) payload.
+const NOTE_RECORD_SIZE: usize = 24;
+
+/// Accumulates pattern events keyed by pattern index.
+///
+/// FL Studio writes the same pattern index more than once (notes in one pass,
+/// name and colour in another), so events are merged per index rather than
+/// appended in stream order.
+#[derive(Default)]
+pub(crate) struct PatternBuilder {
+    patterns: BTreeMap<u16, PatternInfo>,
+    current: Option<u16>,
+}
+
+impl PatternBuilder {
+    /// Start (or resume) the pattern with the given index.
+    pub fn open(&mut self, index: u16) {
+        self.patterns.entry(index).or_insert_with(|| PatternInfo {
+            index,
+            ..Default::default()
+        });
+        self.current = Some(index);
+    }
+
+    /// Leave pattern scope; following events no longer belong to a pattern.
+    pub fn close(&mut self) {
+        self.current = None;
+    }
+
+    pub fn current(&self) -> Option<u16> {
+        self.current
+    }
+
+    fn current_mut(&mut self) -> Option<&mut PatternInfo> {
+        let index = self.current?;
+        self.patterns.get_mut(&index)
+    }
+
+    pub fn set_name(&mut self, name: String) {
+        if let Some(p) = self.current_mut() {
+            p.name = if name.is_empty() { None } else { Some(name) };
+        }
+    }
+
+    /// Colour is stored as little-endian R, G, B, reserved.
+    pub fn set_color(&mut self, value: u32) {
+        if let Some(p) = self.current_mut() {
+            let [r, g, b, _] = value.to_le_bytes();
+            p.color = Some(format!("#{:02x}{:02x}{:02x}", r, g, b));
+        }
+    }
+
+    pub fn set_time_sig_num(&mut self, num: u8) {
+        if let Some(p) = self.current_mut() {
+            p.time_sig_num = Some(num);
+        }
+    }
+
+    pub fn set_time_sig_den(&mut self, den: u8) {
+        if let Some(p) = self.current_mut() {
+            p.time_sig_den = Some(den);
+        }
+    }
+
+    /// Count notes per channel and extend the pattern length to its last note end.
+    pub fn add_notes(&mut self, payload: &[u8]) {
+        let p = match self.current_mut() {
+            Some(p) => p,
+            None => return,
+        };
+        let mut per_channel: BTreeMap<u16, u32> = p
+            .notes_per_channel
+            .iter()
+            .map(|c| (c.channel, c.notes))
+            .collect();
+
+        for note in payload.chunks_exact(NOTE_RECORD_SIZE) {
+            // Record layout: u32 position, u16 flags, u16 channel, u32 length, ...
+            let position = u32::from_le_bytes([note[0], note[1], note[2], note[3]]);
+            let channel = u16::from_le_bytes([note[6], note[7]]);
+            let length = u32::from_le_bytes([note[8], note[9], note[10], note[11]]);
+            *per_channel.entry(channel).or_insert(0) += 1;
+            p.note_count += 1;
+            p.length_ticks = p.length_ticks.max(position.saturating_add(length));
+        }
+
+        p.notes_per_channel = per_channel
+            .into_iter()
+            .map(|(channel, notes)| PatternChannelNotes { channel, notes })
+            .collect();
+    }
+
+    pub fn finish(self) -> Vec<PatternInfo> {
+        self.patterns.into_values().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(position: u32, channel: u16, length: u32) -> Vec<u8> {
+        let mut v = Vec::new();
+        v.extend_from_slice(&position.to_le_bytes());
+        v.extend_from_slice(&0u16.to_le_bytes());
+        v.extend_from_slice(&channel.to_le_bytes());
+        v.extend_from_slice(&length.to_le_bytes());
+        v.extend_from_slice(&[0u8; 12]);
+        v
+    }
+
+    #[test]
+    fn test_note_counts_and_length() {
+        let mut b = PatternBuilder::default();
+        b.open(1);
+        let mut payload = note(0, 0, 48);
+        payload.extend(note(96, 0, 48));
+        payload.extend(note(336, 2, 48));
+        b.add_notes(&payload);
+        let patterns = b.finish();
+        assert_eq!(patterns[0].note_count, 3);
+        assert_eq!(patterns[0].length_ticks, 384);
+        assert_eq!(
+            patterns[0].notes_per_channel,
+            vec![
+                PatternChannelNotes { channel: 0, notes: 2 },
+                PatternChannelNotes { channel: 2, notes: 1 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reopened_pattern_merges() {
+        let mut b = PatternBuilder::default();
+        b.open(3);
+        b.add_notes(&note(0, 1, 24));
+        b.close();
+        b.set_name("ignored outside scope".to_string());
+        b.open(3);
+        b.set_name("Chorus Drums".to_string());
+        b.set_color(0x0033_2211);
+        let patterns = b.finish();
+        assert_eq!(patterns.len(), 1);
+        assert_eq!(patterns[0].name.as_deref(), Some("Chorus Drums"));
+        assert_eq!(patterns[0].color.as_deref(), Some("#112233"));
+        assert_eq!(patterns[0].note_count, 1);
+    }
+}
@@ -0,0 +1,297 @@
+use std::io::{self, Read, Seek, SeekFrom};
+
+use byteorder::{LittleEndian, ReadBytesExt};
+
+use super::flp::decode_string;
+use super::types::{PluginFormat, PluginIdentity};
+
+/// Internal name FL Studio gives every third-party plugin host.
+pub const WRAPPER_INTERNAL_NAME: &str = "Fruity Wrapper";
+
+/// First u32 of a wrapper state payload that holds VST records.
+const VST_STATE_KINDS: [u32; 2] = [8, 10];
+
+// Record IDs inside the wrapper state payload
+const VST_RECORD_FOURCC: u32 = 51;
+const VST_RECORD_GUID: u32 = 52;
+const VST_RECORD_NAME: u32 = 54;
+const VST_RECORD_PATH: u32 = 55;
+const VST_RECORD_VENDOR: u32 = 56;
+
+/// Records larger than this are never identity strings and are not kept.
+const MAX_KEPT_RECORD: u64 = 4096;
+
+/// Plugin details recovered from the wrapper state.
+#[derive(Debug, Default, PartialEq)]
+struct VstInfo {
+    name: Option<String>,
+    vendor: Option<String>,
+    path: Option<String>,
+    fourcc: Option<String>,
+    guid: Option<String>,
+}
+
+fn non_empty(s: String) -> Option<String> {
+    if s.is_empty() {
+        None
+    } else {
+        Some(s)
+    }
+}
+
+/// VST2 unique IDs are usually four printable characters; fall back to hex.
+fn format_fourcc(bytes: &[u8]) -> String {
+    if bytes.iter().all(|b| b.is_ascii_graphic()) {
+        String::from_utf8_lossy(bytes).into_owned()
+    } else {
+        bytes.iter().map(|b| format!("{:02X}", b)).collect()
+    }
+}
+
+/// Decode the wrapper state: u32 kind, then records of
+/// u32 id + u64 size + payload. Returns None for non-VST state.
+fn decode_vst_state(data: &[u8]) -> Option<VstInfo> {
+    let kind = u32::from_le_bytes(data.get(0..4)?.try_into().ok()?);
+    if !VST_STATE_KINDS.contains(&kind) {
+        return None;
+    }
+
+    let mut info = VstInfo::default();
+    let mut pos = 4usize;
+    while pos + 12 <= data.len() {
+        let id = u32::from_le_bytes(data[pos..pos + 4].try_into().ok()?);
+        let size = u64::from_le_bytes(data[pos + 4..pos + 12].try_into().ok()?);
+        pos += 12;
+        let end = match usize::try_from(size).ok().and_then(|s| pos.checked_add(s)) {
+            Some(end) if end <= data.len() => end,
+            _ => break, // truncated record
+        };
+        let value = &data[pos..end];
+        match id {
+            VST_RECORD_NAME => info.name = non_empty(decode_string(value)),
+            VST_RECORD_VENDOR => info.vendor = non_empty(decode_string(value)),
+            VST_RECORD_PATH => info.path = non_empty(decode_string(value)),
+            VST_RECORD_FOURCC if value.len() >= 4 => info.fourcc = Some(format_fourcc(&value[..4])),
+            VST_RECORD_GUID if !value.is_empty() => {
+                info.guid = Some(value.iter().map(|b| format!("{:02X}", b)).collect())
+            }
+            _ => {}
+        }
+        pos = end;
+    }
+    Some(info)
+}
+
+/// Read a `len`-byte plugin state payload, keeping only the records
+/// `decode_vst_state` looks at. The plugin's own chunk (often megabytes of
+/// preset or sample data) is skipped with a seek, so the result is small
+/// whatever the state size. The reader is left at the end of the payload.
+pub(crate) fn read_state<R: Read + Seek>(reader: &mut R, len: u64) -> io::Result<Vec<u8>> {
+    let start = reader.stream_position()?;
+    let end = start + len;
+    let mut kept = Vec::new();
+    if len >= 4 {
+        let kind = reader.read_u32::<LittleEndian>()?;
+        kept.extend_from_slice(&kind.to_le_bytes());
+        if VST_STATE_KINDS.contains(&kind) {
+            let mut pos = start + 4;
+            while pos + 12 <= end {
+                let id = reader.read_u32::<LittleEndian>()?;
+                let size = reader.read_u64::<LittleEndian>()?;
+                pos += 12;
+                if size > end - pos {
+                    break; // truncated record
+                }
+                let wanted = matches!(
+                    id,
+                    VST_RECORD_FOURCC
+                        | VST_RECORD_GUID
+                        | VST_RECORD_NAME
+                        | VST_RECORD_PATH
+                        | VST_RECORD_VENDOR
+                );
+                if wanted && size <= MAX_KEPT_RECORD {
+                    let mut value = vec![0u8; size as usize];
+                    reader.read_exact(&mut value)?;
+                    kept.extend_from_slice(&id.to_le_bytes());
+                    kept.extend_from_slice(&size.to_le_bytes());
+                    kept.extend_from_slice(&value);
+                } else {
+                    reader.seek(SeekFrom::Current(size as i64))?;
+                }
+                pos += size;
+            }
+        }
+    }
+    reader.seek(SeekFrom::Start(end))?;
+    Ok(kept)
+}
+
+/// Accumulates the plugin events of one channel or mixer slot.
+#[derive(Default)]
+pub(crate) struct PluginBuilder {
+    internal_name: Option<String>,
+    display_name: Option<String>,
+    state: Option<Vec<u8>>,
+}
+
+impl PluginBuilder {
+    pub fn set_internal_name(&mut self, name: String) {
+        self.internal_name = non_empty(name);
+    }
+
+    pub fn set_display_name(&mut self, name: String) {
+        self.display_name = non_empty(name);
+    }
+
+    pub fn set_state(&mut self, payload: &[u8]) {
+        self.state = Some(payload.to_vec());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.internal_name.is_none() && self.display_name.is_none() && self.state.is_none()
+    }
+
+    /// Resolve and reset: returns the name shown in FL Studio and the real
+    /// plugin identity, which is unaffected by the user renaming the plugin.
+    pub fn take(&mut self) -> (Option<String>, Option<PluginIdentity>) {
+        let builder = std::mem::take(self);
+        let vst = builder.state.as_deref().and_then(decode_vst_state);
+        let is_wrapper = builder
+            .internal_name
+            .as_deref()
+            .map(|n| n.eq_ignore_ascii_case(WRAPPER_INTERNAL_NAME))
+            .unwrap_or(false);
+
+        let identity = match (vst, builder.internal_name.clone()) {
+            (Some(vst), internal) if is_wrapper || internal.is_none() => {
+                let format = if vst.guid.is_some() {
+                    PluginFormat::Vst3
+                } else if vst.fourcc.is_some() {
+                    PluginFormat::Vst2
+                } else {
+                    PluginFormat::Wrapped
+                };
+                let internal_name = vst
+                    .name
+                    .clone()
+                    .or(internal)
+                    .unwrap_or_else(|| WRAPPER_INTERNAL_NAME.to_string());
+                Some(PluginIdentity {
+                    internal_name,
+                    vendor: vst.vendor,
+                    format,
+                    plugin_id: vst.guid.or(vst.fourcc),
+                    path: vst.path,
+                })
+            }
+            (_, Some(internal)) if is_wrapper => Some(PluginIdentity {
+                internal_name: internal,
+                format: PluginFormat::Wrapped,
+                ..Default::default()
+            }),
+            (_, Some(internal)) => Some(PluginIdentity {
+                internal_name: internal,
+                format: PluginFormat::Native,
+                ..Default::default()
+            }),
+            _ => None,
+        };
+
+        let display_name = builder
+            .display_name
+            .or_else(|| identity.as_ref().map(|i| i.internal_name.clone()));
+        (display_name, identity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vst_record(id: u32, value: &[u8]) -> Vec<u8> {
+        let mut v = id.to_le_bytes().to_vec();
+        v.extend_from_slice(&(value.len() as u64).to_le_bytes());
+        v.extend_from_slice(value);
+        v
+    }
+
+    fn vst_state(records: &[(u32, &[u8])]) -> Vec<u8> {
+        let mut v = 10u32.to_le_bytes().to_vec();
+        for (id, value) in records {
+            v.extend(vst_record(*id, value));
+        }
+        v
+    }
+
+    #[test]
+    fn test_native_plugin_keeps_internal_name_when_renamed() {
+        let mut b = PluginBuilder::default();
+        b.set_internal_name("Sytrus".to_string());
+        b.set_display_name("Lead".to_string());
+        let (display, identity) = b.take();
+        assert_eq!(display.as_deref(), Some("Lead"));
+        let identity = identity.unwrap();
+        assert_eq!(identity.internal_name, "Sytrus");
+        assert_eq!(identity.format, PluginFormat::Native);
+        assert!(b.is_empty(), "take() should reset the builder");
+    }
+
+    #[test]
+    fn test_vst3_identity_from_wrapper_state() {
+        let mut b = PluginBuilder::default();
+        b.set_internal_name("Fruity Wrapper".to_string());
+        b.set_display_name("Big Pad".to_string());
+        b.set_state(&vst_state(&[
+            (VST_RECORD_NAME, b"Serum"),
+            (VST_RECORD_VENDOR, b"Xfer Records"),
+            (VST_RECORD_GUID, &[0xAB; 16]),
+            (VST_RECORD_PATH, b"C:\\VST3\\Serum.vst3"),
+        ]));
+        let (display, identity) = b.take();
+        let identity = identity.unwrap();
+        assert_eq!(display.as_deref(), Some("Big Pad"));
+        assert_eq!(identity.internal_name, "Serum");
+        assert_eq!(identity.vendor.as_deref(), Some("Xfer Records"));
+        assert_eq!(identity.format, PluginFormat::Vst3);
+        assert_eq!(identity.plugin_id.as_deref(), Some("ABABABABABABABABABABABABABABABAB"));
+        assert_eq!(identity.path.as_deref(), Some("C:\\VST3\\Serum.vst3"));
+    }
+
+    #[test]
+    fn test_vst2_fourcc_and_truncated_record() {
+        let mut state = vst_state(&[(VST_RECORD_FOURCC, b"XfsX"), (VST_RECORD_NAME, b"Serum")]);
+        // A record claiming more bytes than remain is ignored
+        state.extend(VST_RECORD_VENDOR.to_le_bytes());
+        state.extend(u64::MAX.to_le_bytes());
+        let info = decode_vst_state(&state).unwrap();
+        assert_eq!(info.fourcc.as_deref(), Some("XfsX"));
+        assert_eq!(info.name.as_deref(), Some("Serum"));
+        assert!(info.vendor.is_none());
+    }
+
+    #[test]
+    fn test_read_state_skips_plugin_chunk() {
+        let state = vst_state(&[
+            (VST_RECORD_FOURCC, b"XfsX"),
+            (53, &[0u8; 100_000]),
+            (VST_RECORD_NAME, b"Serum"),
+        ]);
+        let mut reader = std::io::Cursor::new(&state);
+        let kept = read_state(&mut reader, state.len() as u64).unwrap();
+        assert_eq!(reader.position(), state.len() as u64);
+        assert!(kept.len() < 100);
+        let info = decode_vst_state(&kept).unwrap();
+        assert_eq!(info.fourcc.as_deref(), Some("XfsX"));
+        assert_eq!(info.name.as_deref(), Some("Serum"));
+    }
+
+    #[test]
+    fn test_wrapper_without_state() {
+        let mut b = PluginBuilder::default();
+        b.set_internal_name("Fruity Wrapper".to_string());
+        let (display, identity) = b.take();
+        assert_eq!(display.as_deref(), Some("Fruity Wrapper"));
+        assert_eq!(identity.unwrap().format, PluginFormat::Wrapped);
+    }
+}
@@ -0,0 +1,176 @@
+use chrono::{DateTime, Duration, NaiveDateTime};
+
+/// Days between the Delphi epoch (1899-12-30) and the Unix epoch.
+const DELPHI_EPOCH_OFFSET_DAYS: f64 = 25569.0;
+
+const SECONDS_PER_DAY: f64 = 86400.0;
+
+/// Convert a Delphi `TDateTime` (fractional days since 1899-12-30) to a
+/// naive date-time. FL Studio writes local wall-clock time with no zone.
+fn delphi_to_datetime(days: f64) -> Option<NaiveDateTime> {
+    if !days.is_finite() || days <= 0.0 {
+        return None;
+    }
+    let secs = ((days - DELPHI_EPOCH_OFFSET_DAYS) * SECONDS_PER_DAY).round();
+    if secs < i64::MIN as f64 || secs > i64::MAX as f64 {
+        return None;
+    }
+    DateTime::from_timestamp(secs as i64, 0).map(|dt| dt.naive_utc())
+}
+
+/// Upper bound on plausible work time; anything larger is a corrupt value.
+const MAX_WORK_DAYS: f64 = 365.0 * 100.0;
+
+/// Convert an accumulated work time in fractional days to a duration.
+fn days_to_duration(days: f64) -> Option<Duration> {
+    if !days.is_finite() || !(0.0..=MAX_WORK_DAYS).contains(&days) {
+        return None;
+    }
+    Some(Duration::milliseconds((days * SECONDS_PER_DAY * 1000.0).round() as i64))
+}
+
+/// Decode the project timestamp event: two little-endian f64 values, the
+/// creation date and the accumulated work time, both in Delphi days.
+/// Either part is None if the payload is short or the value is nonsensical.
+pub(crate) fn decode_timestamp(payload: &[u8]) -> (Option<NaiveDateTime>, Option<Duration>) {
+    let read_f64 = |range: std::ops::Range<usize>| -> Option<f64> {
+        let bytes: [u8; 8] = payload.get(range)?.try_into().ok()?;
+        Some(f64::from_le_bytes(bytes))
+    };
+    let created_at = read_f64(0..8).and_then(delphi_to_datetime);
+    let time_spent = read_f64(8..16).and_then(days_to_duration);
+    (created_at, time_spent)
+}
+
+/// Reduce an RTF document to its plain text.
+///
+/// Newer FL Studio versions only store project comments as RTF. This keeps
+/// the visible text and paragraph breaks and drops formatting, font/colour
+/// tables and other destination groups.
+pub(crate) fn rtf_to_plain(rtf: &str) -> String {
+    let mut out = String::new();
+    let mut chars = rtf.chars().peekable();
+    // Brace depth at which a skipped destination group started
+    let mut skip_depth: Option<usize> = None;
+    let mut depth = 0usize;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                depth += 1;
+                // "{\*" marks an ignorable destination
+                if skip_depth.is_none() && chars.peek() == Some(&'\\') {
+                    let mut look = chars.clone();
+                    look.next();
+                    if look.peek() == Some(&'*') {
+                        skip_depth = Some(depth);
+                    }
+                }
+            }
+            '}' => {
+                if skip_depth == Some(depth) {
+                    skip_depth = None;
+                }
+                depth = depth.saturating_sub(1);
+            }
+            '\\' => {
+                let mut word = String::new();
+                while let Some(&n) = chars.peek() {
+                    if n.is_ascii_alphabetic() {
+                        word.push(n);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                if word.is_empty() {
+                    // Escaped symbol such as \\ \{ \} or \'hh
+                    match chars.next() {
+                        Some('\'') => {
+                            let hex: String = chars.by_ref().take(2).collect();
+                            if skip_depth.is_none() {
+                                if let Ok(b) = u8::from_str_radix(&hex, 16) {
+                                    out.push(b as char);
+                                }
+                            }
+                        }
+                        Some(sym) if skip_depth.is_none() && matches!(sym, '\\' | '{' | '}') => {
+                            out.push(sym)
+                        }
+                        _ => {}
+                    }
+                    continue;
+                }
+                // Optional numeric parameter, then one delimiting space
+                while let Some(&n) = chars.peek() {
+                    if n == '-' || n.is_ascii_digit() {
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                if chars.peek() == Some(&' ') {
+                    chars.next();
+                }
+                match word.as_str() {
+                    "fonttbl" | "colortbl" | "stylesheet" | "info" if skip_depth.is_none() => {
+                        skip_depth = Some(depth);
+                    }
+                    "par" | "line" if skip_depth.is_none() => out.push('\n'),
+                    "tab" if skip_depth.is_none() => out.push('\t'),
+                    _ => {}
+                }
+            }
+            '\r' | '\n' => {}
+            _ => {
+                if skip_depth.is_none() {
+                    out.push(c);
+                }
+            }
+        }
+    }
+
+    out.trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delphi_epoch_conversion() {
+        // 2021-01-01 00:00:00 = 44197 days after 1899-12-30
+        let dt = delphi_to_datetime(44197.0).unwrap();
+        assert_eq!(dt.to_string(), "2021-01-01 00:00:00");
+        // Half a day later
+        let dt = delphi_to_datetime(44197.5).unwrap();
+        assert_eq!(dt.to_string(), "2021-01-01 12:00:00");
+    }
+
+    #[test]
+    fn test_timestamp_rejects_garbage() {
+        assert_eq!(decode_timestamp(&[0u8; 4]), (None, None));
+        assert!(decode_timestamp(&0.0f64.to_le_bytes()).0.is_none());
+        assert!(decode_timestamp(&f64::NAN.to_le_bytes()).0.is_none());
+
+        let mut payload = 44197.0f64.to_le_bytes().to_vec();
+        payload.extend_from_slice(&(-1.0f64).to_le_bytes());
+        let (created_at, time_spent) = decode_timestamp(&payload);
+        assert!(created_at.is_some());
+        assert!(time_spent.is_none(), "Negative work time should be rejected");
+    }
+
+    #[test]
+    fn test_work_time_conversion() {
+        let mut payload = 44197.0f64.to_le_bytes().to_vec();
+        payload.extend_from_slice(&(1.0f64 / 24.0 * 3.5).to_le_bytes()); // 3h30m
+        let (_, time_spent) = decode_timestamp(&payload);
+        assert_eq!(time_spent, Some(Duration::minutes(210)));
+    }
+
+    #[test]
+    fn test_rtf_to_plain() {
+        let rtf = r"{\rtf1\ansi\deff0{\fonttbl{\f0\fnil Tahoma;}}{\colortbl ;\red0\green0\blue0;}{\*\generator Msftedit;}\viewkind4\uc1\pard\f0\fs16 Mixdown v2\par Caf\'e9 vocals\par}";
+        assert_eq!(rtf_to_plain(rtf), "Mixdown v2\nCafé vocals");
+    }
+}
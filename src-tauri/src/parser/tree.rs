@@ -0,0 +1,240 @@
+use serde::Serialize;
+
+use super::events::*;
+
+/// Part of the project a group of events describes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScopeKind {
+    Project,
+    Channel,
+    Pattern,
+    Arrangement,
+    MixerInsert,
+    MixerSlot,
+}
+
+/// Events nested by the part of the project they belong to. The project scope
+/// holds events outside any channel, pattern, arrangement or insert; mixer
+/// inserts hold their slots as children.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Scope {
+    pub kind: ScopeKind,
+    /// Channel, pattern or arrangement number from the opening event; insert
+    /// and slot position for mixer scopes.
+    pub index: Option<u16>,
+    pub events: Vec<Event>,
+    pub children: Vec<Scope>,
+}
+
+impl Scope {
+    fn new(kind: ScopeKind, index: Option<u16>) -> Self {
+        Scope {
+            kind,
+            index,
+            events: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    /// Depth-first walk over this scope and all nested scopes.
+    pub fn walk(&self) -> Vec<&Scope> {
+        let mut scopes = vec![self];
+        for child in &self.children {
+            scopes.extend(child.walk());
+        }
+        scopes
+    }
+}
+
+/// Events that describe the insert itself rather than one of its slots.
+fn is_insert_event(event_id: u8) -> bool {
+    matches!(
+        event_id,
+        FLP_INSERT_FLAGS | FLP_TEXT_INSERT_NAME | FLP_INSERT_ROUTING | FLP_INSERT_OUTPUT
+    )
+}
+
+/// Events that can start a mixer insert.
+fn starts_insert(event_id: u8) -> bool {
+    matches!(
+        event_id,
+        FLP_INSERT_FLAGS
+            | FLP_TEXT_INSERT_NAME
+            | FLP_SLOT_INDEX
+            | FLP_TEXT_PLUGIN_NAME
+            | FLP_TEXT_PLUGIN_DISPLAY_NAME
+            | FLP_PLUGIN_STATE
+    )
+}
+
+/// Tracks the open scopes while grouping, following the same section rules
+/// as `parse_flp`: a new channel, pattern or arrangement event closes the
+/// previous scope and ends the mixer section, and the first insert event
+/// starts it. In the mixer each insert runs until its output event and each
+/// slot until its index event.
+struct TreeBuilder {
+    root: Scope,
+    current: Option<Scope>,
+    slot: Option<Scope>,
+    in_mixer: bool,
+    insert_count: u16,
+}
+
+impl TreeBuilder {
+    fn close(&mut self) {
+        self.close_slot(None);
+        if let Some(scope) = self.current.take() {
+            self.root.children.push(scope);
+        }
+    }
+
+    fn open(&mut self, kind: ScopeKind, index: Option<u16>) {
+        self.close();
+        self.current = Some(Scope::new(kind, index));
+    }
+
+    fn close_slot(&mut self, index: Option<u16>) {
+        if let Some(mut slot) = self.slot.take() {
+            slot.index = index;
+            if let Some(insert) = self.current.as_mut() {
+                insert.children.push(slot);
+            }
+        }
+    }
+
+    fn open_insert(&mut self) {
+        self.open(ScopeKind::MixerInsert, Some(self.insert_count));
+        self.insert_count += 1;
+    }
+
+    fn push(&mut self, event: Event) {
+        let id = event.id;
+        let value = event.value().map(|v| v as u16);
+
+        if !self.in_mixer && is_insert_event(id) {
+            self.close();
+            self.in_mixer = true;
+        }
+
+        let opens = match id {
+            FLP_NEW_CHAN => Some(ScopeKind::Channel),
+            FLP_NEW_PAT => Some(ScopeKind::Pattern),
+            FLP_NEW_ARRANGEMENT => Some(ScopeKind::Arrangement),
+            _ => None,
+        };
+        if let Some(kind) = opens {
+            self.in_mixer = false;
+            self.open(kind, value);
+        } else if id == FLP_PLAYLIST
+            && matches!(&self.current, Some(s) if s.kind == ScopeKind::Pattern)
+        {
+            // Playlist events belong to the arrangement, never a pattern
+            self.close();
+        }
+
+        if !self.in_mixer {
+            match self.current.as_mut() {
+                Some(scope) => scope.events.push(event),
+                None => self.root.events.push(event),
+            }
+            return;
+        }
+
+        if self.current.is_none() {
+            if !starts_insert(id) {
+                self.root.events.push(event);
+                return;
+            }
+            self.open_insert();
+        }
+
+        if is_insert_event(id) {
+            let insert = self.current.as_mut().expect("insert is open");
+            insert.events.push(event);
+            if id == FLP_INSERT_OUTPUT {
+                self.close();
+            }
+            return;
+        }
+
+        self.slot
+            .get_or_insert_with(|| Scope::new(ScopeKind::MixerSlot, None))
+            .events
+            .push(event);
+        if id == FLP_SLOT_INDEX {
+            self.close_slot(value);
+        }
+    }
+}
+
+/// Nest a flat event stream into project, channel, pattern, arrangement and
+/// mixer scopes. Every event ends up in exactly one scope, in stream order.
+pub fn group_events<I: IntoIterator<Item = Event>>(events: I) -> Scope {
+    let mut builder = TreeBuilder {
+        root: Scope::new(ScopeKind::Project, None),
+        current: None,
+        slot: None,
+        in_mixer: false,
+        insert_count: 0,
+    };
+    for event in events {
+        builder.push(event);
+    }
+    builder.close();
+    builder.root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(id: u8, payload: &[u8]) -> Event {
+        Event {
+            id,
+            kind: EventKind::of(id),
+            offset: 0,
+            payload: payload.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_scopes_follow_sections() {
+        let events = vec![
+            event(FLP_TEMPO, &140_000u32.to_le_bytes()),
+            event(FLP_NEW_PAT, &[1, 0]),
+            event(FLP_TEXT_PAT_NAME, b"Drums"),
+            event(FLP_NEW_CHAN, &[0, 0]),
+            event(FLP_TEXT_PLUGIN_NAME, b"Sytrus"),
+            event(FLP_INSERT_FLAGS, &[0; 12]),
+            event(FLP_TEXT_PLUGIN_NAME, b"Fruity Limiter"),
+            event(FLP_SLOT_INDEX, &[0, 0]),
+            event(FLP_SLOT_INDEX, &[1, 0]),
+            event(FLP_INSERT_OUTPUT, &[0; 4]),
+            event(FLP_INSERT_FLAGS, &[0; 12]),
+            event(FLP_INSERT_OUTPUT, &[0; 4]),
+        ];
+        let root = group_events(events);
+
+        assert_eq!(root.events.len(), 1);
+        let kinds: Vec<(ScopeKind, Option<u16>)> =
+            root.children.iter().map(|s| (s.kind, s.index)).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (ScopeKind::Pattern, Some(1)),
+                (ScopeKind::Channel, Some(0)),
+                (ScopeKind::MixerInsert, Some(0)),
+                (ScopeKind::MixerInsert, Some(1)),
+            ]
+        );
+        let insert = &root.children[2];
+        assert_eq!(insert.events.len(), 2);
+        assert_eq!(insert.children.len(), 2);
+        assert_eq!(insert.children[0].events.len(), 2);
+        assert_eq!(insert.children[1].index, Some(1));
+        // Nothing dropped or duplicated
+        let total: usize = root.walk().iter().map(|s| s.events.len()).sum();
+        assert_eq!(total, 12);
+    }
+}
@@ -1,22 +1,423 @@
+use chrono::{Duration, NaiveDateTime};
 use serde::{Deserialize, Serialize};
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub enum PluginFormat {
+    /// Image-Line plugin built into FL Studio.
+    #[default]
+    Native,
+    Vst2,
+    Vst3,
+    /// Third-party plugin in the Fruity Wrapper whose format could not be told.
+    Wrapped,
+}
+
+impl PluginFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PluginFormat::Native => "native",
+            PluginFormat::Vst2 => "vst2",
+            PluginFormat::Vst3 => "vst3",
+            PluginFormat::Wrapped => "wrapped",
+        }
+    }
+}
+
+/// What a plugin actually is, independent of the name the user gave it.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct PluginIdentity {
+    /// Native plugin name (e.g. "Sytrus") or the VST product name.
+    pub internal_name: String,
+    pub vendor: Option<String>,
+    pub format: PluginFormat,
+    /// VST2 unique ID or VST3 class GUID.
+    pub plugin_id: Option<String>,
+    /// Plugin binary path recorded by the wrapper.
+    pub path: Option<String>,
+}
+
+/// Kind of channel rack channel.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChannelType {
+    #[default]
+    Sampler,
+    /// Channel hosting a native or third-party instrument plugin.
+    Generator,
+    AudioClip,
+    Automation,
+    Layer,
+    /// Type byte this parser does not know.
+    Unknown(u8),
+}
+
+impl ChannelType {
+    /// Map the channel type event value. Audio clips share the native
+    /// generator value and are told apart once the whole channel is read.
+    pub fn from_raw(value: u8) -> Self {
+        match value {
+            0 => ChannelType::Sampler,
+            2 | 4 => ChannelType::Generator,
+            3 => ChannelType::Layer,
+            5 => ChannelType::Automation,
+            other => ChannelType::Unknown(other),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ChannelInfo {
+    /// Channel rack index.
+    pub index: u16,
     pub name: String,
+    /// Plugin name as shown in FL Studio; the user can rename it.
     pub plugin_name: Option<String>,
-    pub channel_type: u8,
+    pub plugin: Option<PluginIdentity>,
+    pub channel_type: ChannelType,
+    /// Sample files referenced by this channel, as written by FL Studio
+    /// (may contain placeholders such as `%FLStudioFactoryData%`).
+    pub sample_paths: Vec<String>,
+    /// Mixer insert the channel plays into, if set.
+    pub insert: Option<u16>,
+}
+
+/// Parameter an automation clip drives.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AutomationTarget {
+    /// A parameter of a channel rack channel or its plugin.
+    Channel {
+        channel: u16,
+        parameter: u16,
+        plugin_parameter: bool,
+    },
+    /// A parameter of a mixer insert slot's effect plugin.
+    InsertSlot {
+        insert: u16,
+        slot: u16,
+        parameter: u16,
+        plugin_parameter: bool,
+    },
+}
+
+/// An automation clip channel. Kept apart from generators since it makes
+/// no sound of its own.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct AutomationClip {
+    /// Channel rack index of the clip.
+    pub channel: u16,
+    pub name: String,
+    pub point_count: u32,
+    /// None if the clip is not linked to anything.
+    pub target: Option<AutomationTarget>,
+}
+
+/// Number of notes a pattern plays on one channel rack channel.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct PatternChannelNotes {
+    pub channel: u16,
+    pub notes: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct PatternInfo {
+    pub index: u16,
+    pub name: Option<String>,
+    /// Pattern colour as `#rrggbb`.
+    pub color: Option<String>,
+    /// End of the last note, in ticks.
+    pub length_ticks: u32,
+    /// `length_ticks` in beats at the file's PPQ; None if the PPQ is unusable.
+    pub length_beats: Option<f32>,
+    pub note_count: u32,
+    pub notes_per_channel: Vec<PatternChannelNotes>,
+    /// Time signature override; None means the project default applies.
+    pub time_sig_num: Option<u8>,
+    pub time_sig_den: Option<u8>,
+}
+
+/// An occupied effect slot on a mixer insert.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct MixerSlot {
+    pub index: u16,
+    /// Plugin name as shown in FL Studio; the user can rename it.
+    pub plugin_name: Option<String>,
+    pub plugin: Option<PluginIdentity>,
+    pub enabled: bool,
+}
+
+/// A mixer insert track (index 0 is the master) and its effect slots.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct MixerInsert {
+    pub index: u16,
+    pub name: Option<String>,
+    pub enabled: bool,
+    pub slots: Vec<MixerSlot>,
+    /// Inserts this insert sends to.
+    pub routes_to: Vec<u16>,
+}
+
+/// A channel and the mixer insert it plays into.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ChannelRoute {
+    pub channel: u16,
+    pub name: String,
+    pub insert: u16,
+}
+
+/// A mixer insert and the inserts it sends to.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct InsertRoute {
+    pub insert: u16,
+    pub name: Option<String>,
+    pub targets: Vec<u16>,
+}
+
+/// Signal flow from channels through mixer inserts to the master (insert 0).
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct RoutingGraph {
+    pub channels: Vec<ChannelRoute>,
+    pub inserts: Vec<InsertRoute>,
+}
+
+impl RoutingGraph {
+    /// Inserts a channel's signal passes through, following the first send of
+    /// each insert until it reaches the master or revisits an insert.
+    pub fn chain(&self, channel: u16) -> Vec<u16> {
+        let mut chain = Vec::new();
+        let mut next = self
+            .channels
+            .iter()
+            .find(|c| c.channel == channel)
+            .map(|c| c.insert);
+        while let Some(insert) = next {
+            if chain.contains(&insert) {
+                break;
+            }
+            chain.push(insert);
+            if insert == 0 {
+                break;
+            }
+            next = self
+                .inserts
+                .iter()
+                .find(|i| i.insert == insert)
+                .and_then(|i| i.targets.first().copied());
+        }
+        chain
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub enum ClipKind {
+    #[default]
+    Pattern,
+    /// Audio clip or automation clip, referenced by channel index.
+    Channel,
+}
+
+/// One clip placed on the playlist.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct PlaylistClip {
+    pub kind: ClipKind,
+    /// Pattern index for pattern clips, channel index otherwise.
+    pub index: u16,
+    /// 1-based playlist track, as shown in FL Studio.
+    pub track: u16,
+    pub position_ticks: u32,
+    pub length_ticks: u32,
+}
+
+/// A playlist track the user has named.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct PlaylistTrack {
+    /// 1-based playlist track, as shown in FL Studio.
+    pub index: u16,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct Arrangement {
+    pub index: u16,
+    pub name: Option<String>,
+    pub tracks: Vec<PlaylistTrack>,
+    pub clips: Vec<PlaylistClip>,
+    /// End of the last clip, in ticks.
+    pub length_ticks: u32,
+    /// `length_ticks` in beats at the file's PPQ; None if the PPQ is unusable.
+    pub length_beats: Option<f32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct FlpMetadata {
+    /// Header format; 0 for projects, other values for score/preset files.
+    pub format: u16,
+    /// Ticks per quarter note, from the header.
+    pub ppq: u16,
     pub bpm: Option<f32>,
     pub time_sig_num: Option<u8>,
     pub time_sig_den: Option<u8>,
     pub channel_count: u16,
     pub pattern_count: u16,
+    pub patterns: Vec<PatternInfo>,
     pub mixer_track_count: u16,
+    /// Channel rack channels other than automation clips.
     pub generators: Vec<ChannelInfo>,
+    pub automation: Vec<AutomationClip>,
     pub effects: Vec<String>,
+    pub mixer: Vec<MixerInsert>,
+    pub routing: RoutingGraph,
+    pub arrangements: Vec<Arrangement>,
+    /// Length of the longest arrangement, in bars of the project time signature.
+    pub song_length_bars: Option<f32>,
+    /// Length of the longest arrangement at the project tempo.
+    pub song_length_secs: Option<f32>,
     pub fl_version: Option<String>,
-    pub warnings: Vec<String>,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub genre: Option<String>,
+    pub comments: Option<String>,
+    pub url: Option<String>,
+    /// Project creation date as local wall-clock time (FL Studio stores no zone).
+    pub created_at: Option<NaiveDateTime>,
+    /// Accumulated time the project has been open in FL Studio.
+    #[serde(with = "duration_secs")]
+    pub time_spent: Option<Duration>,
+    pub warnings: Vec<ParseWarning>,
+    /// Event IDs the parser skipped, by ID.
+    pub unknown_events: Vec<UnknownEvent>,
+}
+
+/// Occurrences of one unhandled event ID in a file.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct UnknownEvent {
+    pub event_id: u8,
+    pub count: u32,
+    /// Smallest and largest payload size in bytes.
+    pub min_size: u32,
+    pub max_size: u32,
+    /// Leading payload bytes of the first occurrence.
+    pub sample: Vec<u8>,
+}
+
+/// What a parse warning is about. Stable identifiers, stored and filtered on.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum WarningCode {
+    /// The stream ended inside an event; later data is missing.
+    Truncated,
+    /// No event data chunk after the header.
+    MissingData,
+    /// The file could not be parsed at all.
+    ParseFailed,
+    /// The file could not be read from disk.
+    Unreadable,
+    MissingBpm,
+    TempoOutOfRange,
+    TimeSigOutOfRange,
+    /// Header format is not a regular project.
+    NotAProject,
+    UnusualPpq,
+    /// An event payload has an unexpected size or nonsensical values.
+    MalformedEvent,
+}
+
+impl WarningCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WarningCode::Truncated => "truncated",
+            WarningCode::MissingData => "missing_data",
+            WarningCode::ParseFailed => "parse_failed",
+            WarningCode::Unreadable => "unreadable",
+            WarningCode::MissingBpm => "missing_bpm",
+            WarningCode::TempoOutOfRange => "tempo_out_of_range",
+            WarningCode::TimeSigOutOfRange => "time_sig_out_of_range",
+            WarningCode::NotAProject => "not_a_project",
+            WarningCode::UnusualPpq => "unusual_ppq",
+            WarningCode::MalformedEvent => "malformed_event",
+        }
+    }
+
+    /// How bad the problem usually is: errors mean data was lost.
+    pub fn severity(&self) -> Severity {
+        match self {
+            WarningCode::Truncated
+            | WarningCode::MissingData
+            | WarningCode::ParseFailed
+            | WarningCode::Unreadable => Severity::Error,
+            WarningCode::UnusualPpq => Severity::Info,
+            _ => Severity::Warning,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+/// A non-fatal problem found while parsing. Event warnings carry the byte
+/// offset of the event ID in the file and the event ID itself.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ParseWarning {
+    pub code: WarningCode,
+    pub severity: Severity,
+    pub offset: Option<u64>,
+    pub event_id: Option<u8>,
+    pub message: String,
+}
+
+impl ParseWarning {
+    pub fn new(code: WarningCode, message: impl Into<String>) -> Self {
+        ParseWarning {
+            code,
+            severity: code.severity(),
+            offset: None,
+            event_id: None,
+            message: message.into(),
+        }
+    }
+
+    /// Attach the location of the offending event.
+    pub fn at(mut self, offset: u64, event_id: u8) -> Self {
+        self.offset = Some(offset);
+        self.event_id = Some(event_id);
+        self
+    }
+}
+
+impl std::fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.offset {
+            Some(offset) => write!(f, "{} (at byte {})", self.message, offset),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Serialize an optional duration as whole seconds.
+mod duration_secs {
+    use chrono::Duration;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(d: &Option<Duration>, s: S) -> Result<S::Ok, S::Error> {
+        d.map(|d| d.num_seconds()).serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Duration>, D::Error> {
+        Ok(Option::<i64>::deserialize(d)?.map(Duration::seconds))
+    }
 }
@@ -0,0 +1,180 @@
+use crate::parser::{self, FlpMetadata};
+use std::fs::File;
+use std::io::{BufReader, Read, Seek};
+use std::path::Path;
+use zip::ZipArchive;
+
+/// Folder macOS adds to zips it creates; its entries are metadata, not files.
+const MACOS_METADATA_DIR: &str = "__MACOSX/";
+
+/// Largest embedded project read into memory. Real projects are a few MB;
+/// anything bigger is a corrupt or hostile archive.
+const MAX_PROJECT_ENTRY: u64 = 256 * 1024 * 1024;
+
+/// A project exported with FL Studio's "Export → Project data files": the
+/// .flp plus the samples it uses, zipped.
+#[derive(Debug)]
+pub struct ZipProject {
+    pub meta: FlpMetadata,
+    /// Archive paths of every other file in the zip.
+    pub bundled: Vec<String>,
+}
+
+pub fn is_zip(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.eq_ignore_ascii_case("zip"))
+        .unwrap_or(false)
+}
+
+/// Files in the archive, skipping directories and macOS metadata.
+fn file_entries<R: Read + Seek>(archive: &ZipArchive<R>) -> Vec<String> {
+    archive
+        .file_names()
+        .filter(|name| !name.ends_with('/') && !name.starts_with(MACOS_METADATA_DIR))
+        .map(str::to_string)
+        .collect()
+}
+
+/// The embedded project: the shallowest .flp entry, first in archive order on
+/// a tie.
+fn project_entry(entries: &[String]) -> Option<&String> {
+    entries
+        .iter()
+        .filter(|name| name.to_lowercase().ends_with(".flp"))
+        .min_by_key(|name| name.matches('/').count())
+}
+
+fn open_archive(path: &Path) -> Result<ZipArchive<BufReader<File>>, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    ZipArchive::new(BufReader::new(file)).map_err(|e| e.to_string())
+}
+
+/// Whether a zip holds an FL Studio project. Only the archive's central
+/// directory is read.
+pub fn is_project_zip(path: &Path) -> bool {
+    open_archive(path)
+        .map(|archive| project_entry(&file_entries(&archive)).is_some())
+        .unwrap_or(false)
+}
+
+/// Decompress one entry into memory, refusing entries over `limit` bytes.
+/// Compressed entries cannot seek, so the project has to be read whole; the
+/// samples next to it are never decompressed.
+fn read_entry<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    name: &str,
+    limit: u64,
+) -> Result<Vec<u8>, String> {
+    let too_large = || format!("{} is larger than {} MB", name, limit / (1024 * 1024));
+    let entry = archive.by_name(name).map_err(|e| e.to_string())?;
+    if entry.size() > limit {
+        return Err(too_large());
+    }
+    // The declared size comes from the archive, so the read is capped too
+    let mut bytes = Vec::new();
+    entry
+        .take(limit + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|e| e.to_string())?;
+    if bytes.len() as u64 > limit {
+        return Err(too_large());
+    }
+    Ok(bytes)
+}
+
+/// Parse the project inside a zip without extracting anything to disk.
+pub fn read_zip_project<R: Read + Seek>(reader: R) -> Result<ZipProject, String> {
+    let mut archive = ZipArchive::new(BufReader::new(reader)).map_err(|e| e.to_string())?;
+    let entries = file_entries(&archive);
+    let entry = project_entry(&entries)
+        .cloned()
+        .ok_or_else(|| "Archive contains no .flp project".to_string())?;
+
+    let bytes = read_entry(&mut archive, &entry, MAX_PROJECT_ENTRY)?;
+    let meta = parser::parse_flp(&bytes).map_err(|e| e.to_string())?;
+
+    let bundled = entries.into_iter().filter(|name| *name != entry).collect();
+    Ok(ZipProject { meta, bundled })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::tempdir;
+    use zip::write::SimpleFileOptions;
+
+    fn write_zip(path: &Path, entries: &[(&str, &[u8])]) {
+        let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+        for (name, data) in entries {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(data).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    fn minimal_flp() -> Vec<u8> {
+        let mut v = b"FLhd".to_vec();
+        v.extend_from_slice(&[6, 0, 0, 0, 0, 0, 1, 0, 96, 0]);
+        v.extend_from_slice(b"FLdt");
+        v.extend_from_slice(&5u32.to_le_bytes());
+        v.extend_from_slice(&[156, 0xe0, 0x22, 0x02, 0x00]);
+        v
+    }
+
+    #[test]
+    fn test_reads_embedded_project_and_bundle() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("Song.zip");
+        let flp = minimal_flp();
+        write_zip(
+            &path,
+            &[
+                ("Song/Samples/kick.wav", b"RIFF"),
+                ("Song/Song.flp", &flp),
+                ("__MACOSX/Song/._Song.flp", b""),
+            ],
+        );
+
+        assert!(is_project_zip(&path));
+        let project = read_zip_project(File::open(&path).unwrap()).unwrap();
+        assert_eq!(project.meta.bpm, Some(140.0));
+        assert_eq!(project.bundled, vec!["Song/Samples/kick.wav"]);
+    }
+
+    #[test]
+    fn test_oversized_project_entry_is_refused() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("Song.zip");
+        let flp = minimal_flp();
+        write_zip(&path, &[("Song.flp", &flp)]);
+
+        let mut archive = open_archive(&path).unwrap();
+        let limit = flp.len() as u64;
+        assert_eq!(read_entry(&mut archive, "Song.flp", limit).unwrap(), flp);
+        let err = read_entry(&mut archive, "Song.flp", limit - 1).unwrap_err();
+        assert!(err.contains("larger than"), "{}", err);
+    }
+
+    #[test]
+    fn test_shallowest_project_entry_wins() {
+        let entries = vec![
+            "Song/Backup/Song (autosave).flp".to_string(),
+            "Song/Song.flp".to_string(),
+            "Song/Song 2.flp".to_string(),
+        ];
+        assert_eq!(project_entry(&entries).map(String::as_str), Some("Song/Song.flp"));
+    }
+
+    #[test]
+    fn test_zip_without_project_is_skipped() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("samples.zip");
+        write_zip(&path, &[("kick.wav", b"RIFF")]);
+        assert!(!is_project_zip(&path));
+        assert!(read_zip_project(File::open(&path).unwrap()).is_err());
+        std::fs::write(dir.path().join("broken.zip"), b"not a zip").unwrap();
+        assert!(!is_project_zip(&dir.path().join("broken.zip")));
+    }
+}
@@ -1,9 +1,8 @@
 use crate::matcher::{propose_groups, ProposedGroup};
 use crate::store::files::list_all_files;
-use rusqlite::Connection;
-use std::sync::Mutex;
+use crate::store::{Db, StoreError};
 
-pub fn run_grouper(db: &Mutex<Connection>, threshold: f32) -> Vec<ProposedGroup> {
-    let files = list_all_files(db);
-    propose_groups(&files, threshold)
+pub fn run_grouper(db: &Db, threshold: f32) -> Result<Vec<ProposedGroup>, StoreError> {
+    let files = list_all_files(db)?;
+    Ok(propose_groups(&files, threshold))
 }
@@ -1,2 +1,5 @@
+pub mod archive;
 pub mod grouper;
+pub mod plugins;
+pub mod samples;
 pub mod scanner;
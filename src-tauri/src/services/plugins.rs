@@ -0,0 +1,308 @@
+use crate::parser::types::PluginFormat;
+use crate::parser::WRAPPER_INTERNAL_NAME;
+use crate::store::plugins::{list_all_plugin_refs, list_installed_plugins, InstalledPlugin};
+use crate::store::{Db, StoreError};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+use walkdir::WalkDir;
+
+/// Tokens that installers append to plugin file names but FL Studio does not
+/// record in the plugin name (e.g. "Serum_x64.dll" for "Serum").
+const NAME_NOISE_TOKENS: &[&str] = &["x64", "x86", "64bit", "32bit", "vst", "vst2", "vst3", "clap"];
+
+/// One entry of the user's plugin manifest. Only `name` is required.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ManifestEntry {
+    pub name: String,
+    pub vendor: Option<String>,
+    pub format: Option<String>,
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PluginScanResult {
+    pub installed: usize,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MissingPlugin {
+    pub internal_name: String,
+    pub vendor: Option<String>,
+    pub format: String,
+    /// Names the plugin was given in this project, if renamed.
+    pub display_names: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectPluginReport {
+    pub hash: String,
+    pub project_path: String,
+    pub missing: Vec<MissingPlugin>,
+}
+
+/// Normalise a plugin or file name for matching: lowercase alphanumeric
+/// words with architecture/format suffixes dropped.
+pub fn plugin_key(name: &str) -> String {
+    name.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty() && !NAME_NOISE_TOKENS.contains(t))
+        .collect()
+}
+
+/// File name without extension. Paths recorded by FL Studio use Windows
+/// separators, so both kinds are accepted on every OS.
+fn file_stem(path: &str) -> Option<&str> {
+    let file = path.rsplit(['\\', '/']).next()?;
+    let stem = file.rsplit_once('.').map(|(stem, _)| stem).unwrap_or(file);
+    if stem.is_empty() {
+        None
+    } else {
+        Some(stem)
+    }
+}
+
+/// Plugin format for a file or bundle, by extension.
+fn plugin_format(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_lowercase();
+    match ext.as_str() {
+        "vst3" => Some("vst3"),
+        "clap" => Some("clap"),
+        "dll" | "vst" | "so" => Some("vst2"),
+        _ => None,
+    }
+}
+
+/// Walk the plugin folders. VST3, CLAP and macOS VST bundles are directories
+/// and are recorded as one plugin without descending into them.
+pub fn scan_plugin_folders(folders: &[String]) -> Vec<InstalledPlugin> {
+    let mut plugins = Vec::new();
+    for folder in folders.iter().filter(|f| !f.is_empty()) {
+        let mut walker = WalkDir::new(folder).follow_links(true).into_iter();
+        while let Some(entry) = walker.next() {
+            let entry = match entry {
+                Ok(e) => e,
+                Err(_) => continue,
+            };
+            let format = match plugin_format(entry.path()) {
+                Some(f) => f,
+                None => continue,
+            };
+            if entry.file_type().is_dir() {
+                walker.skip_current_dir();
+            }
+            let name = entry
+                .path()
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            plugins.push(InstalledPlugin {
+                name,
+                vendor: None,
+                format: format.to_string(),
+                path: entry.path().to_string_lossy().into_owned(),
+                source: "scan".to_string(),
+            });
+        }
+    }
+    plugins
+}
+
+/// Read the user's manifest: a JSON array of `{name, vendor?, format?, path?}`.
+pub fn read_manifest(path: &Path) -> Result<Vec<InstalledPlugin>, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("Cannot read plugin manifest {}: {}", path.display(), e))?;
+    let entries: Vec<ManifestEntry> = serde_json::from_str(&text)
+        .map_err(|e| format!("Invalid plugin manifest {}: {}", path.display(), e))?;
+    Ok(entries
+        .into_iter()
+        .map(|e| InstalledPlugin {
+            // Manifest entries without a path are keyed by name
+            path: e.path.unwrap_or_else(|| format!("manifest:{}", e.name)),
+            name: e.name,
+            vendor: e.vendor,
+            format: e.format.unwrap_or_else(|| "vst2".to_string()),
+            source: "manifest".to_string(),
+        })
+        .collect())
+}
+
+/// Rebuild the installed-plugin table from the folders and manifest.
+pub fn refresh_installed_plugins(
+    db: &Db,
+    folders: &[String],
+    manifest: Option<&Path>,
+) -> Result<PluginScanResult, StoreError> {
+    let mut warnings = Vec::new();
+    let mut plugins = scan_plugin_folders(folders);
+    if let Some(manifest) = manifest {
+        match read_manifest(manifest) {
+            Ok(entries) => plugins.extend(entries),
+            Err(e) => warnings.push(e),
+        }
+    }
+    crate::store::plugins::replace_installed_plugins(db, &plugins)?;
+    Ok(PluginScanResult {
+        installed: plugins.len(),
+        warnings,
+    })
+}
+
+/// Report, per project, the third-party plugins that match nothing in the
+/// installed-plugin table. Native FL Studio plugins are always available.
+pub fn find_missing_plugins(db: &Db) -> Result<Vec<ProjectPluginReport>, StoreError> {
+    let installed: HashSet<String> = list_installed_plugins(db)?
+        .iter()
+        .map(|p| plugin_key(&p.name))
+        .collect();
+    let mut reports: BTreeMap<String, ProjectPluginReport> = BTreeMap::new();
+
+    for plugin_ref in list_all_plugin_refs(db)? {
+        // Unresolved wrappers carry no identity to look up
+        let is_wrapper = plugin_ref.internal_name.eq_ignore_ascii_case(WRAPPER_INTERNAL_NAME);
+        if plugin_ref.format == PluginFormat::Native.as_str() || is_wrapper {
+            continue;
+        }
+        let by_name = installed.contains(&plugin_key(&plugin_ref.internal_name));
+        let by_path = plugin_ref
+            .plugin_path
+            .as_deref()
+            .and_then(file_stem)
+            .map(|stem| installed.contains(&plugin_key(stem)))
+            .unwrap_or(false);
+        if by_name || by_path {
+            continue;
+        }
+
+        let report = reports
+            .entry(plugin_ref.hash.clone())
+            .or_insert_with(|| ProjectPluginReport {
+                hash: plugin_ref.hash,
+                project_path: plugin_ref.project_path,
+                missing: Vec::new(),
+            });
+        let missing = match report
+            .missing
+            .iter_mut()
+            .find(|m| m.internal_name == plugin_ref.internal_name)
+        {
+            Some(m) => m,
+            None => {
+                report.missing.push(MissingPlugin {
+                    internal_name: plugin_ref.internal_name.clone(),
+                    vendor: plugin_ref.vendor,
+                    format: plugin_ref.format,
+                    display_names: Vec::new(),
+                });
+                report.missing.last_mut().unwrap()
+            }
+        };
+        if let Some(name) = plugin_ref.display_name {
+            if name != missing.internal_name && !missing.display_names.contains(&name) {
+                missing.display_names.push(name);
+            }
+        }
+    }
+
+    Ok(reports.into_values().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::types::{
+        ChannelInfo, FlpMetadata, MixerInsert, MixerSlot, PluginIdentity,
+    };
+    use crate::store::connection::init_db;
+    use crate::store::test_support::{parsed, store_project};
+    use tempfile::tempdir;
+
+    fn identity(name: &str, format: PluginFormat) -> Option<PluginIdentity> {
+        Some(PluginIdentity {
+            internal_name: name.to_string(),
+            format,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_plugin_key_ignores_suffixes() {
+        assert_eq!(plugin_key("Serum_x64"), plugin_key("Serum"));
+        assert_eq!(plugin_key("FabFilter Pro-Q 3"), "fabfilterproq3");
+        assert_eq!(file_stem("C:\\VST\\Serum_x64.dll"), Some("Serum_x64"));
+    }
+
+    #[test]
+    fn test_scan_records_bundles_once() {
+        let dir = tempdir().unwrap();
+        let bundle = dir.path().join("Serum.vst3").join("Contents").join("x86_64-win");
+        std::fs::create_dir_all(&bundle).unwrap();
+        std::fs::write(bundle.join("Serum.vst3"), b"MZ").unwrap();
+        std::fs::write(dir.path().join("Vital.clap"), b"MZ").unwrap();
+        std::fs::write(dir.path().join("readme.txt"), b"").unwrap();
+
+        let mut names: Vec<String> = scan_plugin_folders(&[dir.path().to_string_lossy().into()])
+            .into_iter()
+            .map(|p| format!("{}:{}", p.format, p.name))
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["clap:Vital", "vst3:Serum"]);
+    }
+
+    #[test]
+    fn test_find_missing_plugins() {
+        let dir = tempdir().unwrap();
+        let db = init_db(dir.path()).unwrap();
+        let plugins_dir = dir.path().join("vst");
+        std::fs::create_dir_all(&plugins_dir).unwrap();
+        std::fs::write(plugins_dir.join("Serum_x64.dll"), b"MZ").unwrap();
+        let manifest = dir.path().join("manifest.json");
+        std::fs::write(&manifest, r#"[{"name": "Kontakt 7", "vendor": "NI"}]"#).unwrap();
+
+        let meta = FlpMetadata {
+            generators: vec![
+                ChannelInfo {
+                    plugin_name: Some("Bass".to_string()),
+                    plugin: identity("Serum", PluginFormat::Vst2),
+                    ..Default::default()
+                },
+                ChannelInfo {
+                    plugin_name: Some("Sytrus".to_string()),
+                    plugin: identity("Sytrus", PluginFormat::Native),
+                    ..Default::default()
+                },
+                ChannelInfo {
+                    plugin: identity("Kontakt 7", PluginFormat::Vst3),
+                    ..Default::default()
+                },
+                ChannelInfo {
+                    // Unresolved wrapper, recorded in lowercase by some versions
+                    plugin: identity("fruity wrapper", PluginFormat::Wrapped),
+                    ..Default::default()
+                },
+            ],
+            mixer: vec![MixerInsert {
+                slots: vec![MixerSlot {
+                    plugin_name: Some("Vocal EQ".to_string()),
+                    plugin: identity("Pro-Q 3", PluginFormat::Vst3),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        store_project(&db, parsed("abc", "/song.flp", &meta));
+
+        let folders = vec![plugins_dir.to_string_lossy().into_owned()];
+        let result = refresh_installed_plugins(&db, &folders, Some(&manifest)).unwrap();
+        assert_eq!(result.installed, 2);
+        assert!(result.warnings.is_empty());
+
+        let reports = find_missing_plugins(&db).unwrap();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].missing.len(), 1);
+        assert_eq!(reports[0].missing[0].internal_name, "Pro-Q 3");
+        assert_eq!(reports[0].missing[0].display_names, vec!["Vocal EQ"]);
+    }
+}
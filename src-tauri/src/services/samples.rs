@@ -0,0 +1,254 @@
+use crate::store::bundles::{bundled_name, list_bundled_names};
+use crate::store::samples::list_all_samples;
+use crate::store::settings::Settings;
+use crate::store::{Db, StoreError};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MissingSample {
+    pub channel_name: String,
+    pub path: String,
+}
+
+/// Sample problems for one project. `missing` paths resolved but do not exist
+/// on disk; `unresolved` paths use a placeholder that could not be expanded.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectSampleReport {
+    pub hash: String,
+    pub project_path: String,
+    pub missing: Vec<MissingSample>,
+    pub unresolved: Vec<MissingSample>,
+}
+
+/// Folders FL Studio's own path placeholders stand for. These are not
+/// environment variables; FL Studio fills them in itself.
+#[derive(Debug, Clone, Default)]
+pub struct FlFolders {
+    /// `%FLStudioFactoryData%`: the FL Studio install folder.
+    pub factory_data: String,
+    /// `%FLStudioUserData%`: Documents\Image-Line\FL Studio by default.
+    pub user_data: String,
+}
+
+impl FlFolders {
+    pub fn from_settings(settings: &Settings) -> Self {
+        FlFolders {
+            factory_data: settings.fl_factory_data_folder.clone(),
+            user_data: settings.fl_user_data_folder.clone(),
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<String> {
+        let folder = if name.eq_ignore_ascii_case("FLStudioFactoryData") {
+            &self.factory_data
+        } else if name.eq_ignore_ascii_case("FLStudioUserData") {
+            &self.user_data
+        } else {
+            return std::env::var(name).ok();
+        };
+        if folder.is_empty() {
+            None
+        } else {
+            Some(folder.clone())
+        }
+    }
+}
+
+/// Whether `%name%` can be a placeholder rather than two literal percent
+/// signs, as in "50% wet 100%.wav".
+fn is_placeholder_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Expand FL Studio's `%FLStudio...%` placeholders and `%VAR%` environment
+/// variables, and anchor relative paths at the project's folder. Returns None
+/// if a placeholder is not set.
+pub fn resolve_sample_path(raw: &str, project_dir: &Path, fl: &FlFolders) -> Option<PathBuf> {
+    let mut expanded = String::new();
+    let mut rest = raw;
+    while let Some(start) = rest.find('%') {
+        let after = &rest[start + 1..];
+        let end = match after.find('%') {
+            Some(e) => e,
+            None => break,
+        };
+        expanded.push_str(&rest[..start]);
+        let name = &after[..end];
+        if !is_placeholder_name(name) {
+            // Keep the percent sign and look for a placeholder after it
+            expanded.push('%');
+            rest = after;
+            continue;
+        }
+        expanded.push_str(&fl.lookup(name)?);
+        rest = &after[end + 1..];
+    }
+    expanded.push_str(rest);
+
+    let path = PathBuf::from(expanded);
+    if path.is_absolute() {
+        Some(path)
+    } else {
+        Some(project_dir.join(path))
+    }
+}
+
+/// Check every stored sample reference on disk and report the projects that
+/// have at least one missing or unresolvable sample. Samples shipped inside a
+/// zipped project count as present.
+pub fn find_missing_samples(
+    db: &Db,
+    fl: &FlFolders,
+) -> Result<Vec<ProjectSampleReport>, StoreError> {
+    let samples = list_all_samples(db)?;
+    let bundled = list_bundled_names(db)?;
+    // Many projects share the same samples; stat each path only once
+    let mut exists_cache: HashMap<PathBuf, bool> = HashMap::new();
+    let mut reports: BTreeMap<String, ProjectSampleReport> = BTreeMap::new();
+
+    for sample in samples {
+        let project_dir = Path::new(&sample.project_path)
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .to_path_buf();
+        let entry = MissingSample {
+            channel_name: sample.channel_name,
+            path: sample.path,
+        };
+
+        let in_bundle = bundled
+            .get(&sample.hash)
+            .map(|names| names.contains(&bundled_name(&entry.path)))
+            .unwrap_or(false);
+        if in_bundle {
+            continue;
+        }
+
+        let resolved = resolve_sample_path(&entry.path, &project_dir, fl);
+        let problem = match &resolved {
+            None => Some(false),
+            Some(p) => {
+                let exists = *exists_cache.entry(p.clone()).or_insert_with(|| p.exists());
+                if exists {
+                    None
+                } else {
+                    Some(true)
+                }
+            }
+        };
+
+        if let Some(is_missing) = problem {
+            let report = reports
+                .entry(sample.hash.clone())
+                .or_insert_with(|| ProjectSampleReport {
+                    hash: sample.hash,
+                    project_path: sample.project_path,
+                    missing: Vec::new(),
+                    unresolved: Vec::new(),
+                });
+            if is_missing {
+                report.missing.push(entry);
+            } else {
+                report.unresolved.push(entry);
+            }
+        }
+    }
+
+    Ok(reports.into_values().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::types::{ChannelInfo, FlpMetadata};
+    use crate::store::connection::init_db;
+    use crate::store::batch::ParsedFile;
+    use crate::store::test_support::{parsed, store_project};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_relative_path_anchored_at_project() {
+        let fl = FlFolders::default();
+        let resolved = resolve_sample_path("kick.wav", Path::new("/projects/song"), &fl).unwrap();
+        assert_eq!(resolved, PathBuf::from("/projects/song/kick.wav"));
+    }
+
+    #[test]
+    fn test_unknown_placeholder_is_unresolved() {
+        let fl = FlFolders::default();
+        assert!(resolve_sample_path("%FLPV_NOT_SET_VAR%/kick.wav", Path::new("/"), &fl).is_none());
+    }
+
+    #[test]
+    fn test_fl_placeholders_use_configured_folders() {
+        let fl = FlFolders {
+            factory_data: "/opt/FL Studio 21".to_string(),
+            user_data: "/home/me/FL Studio".to_string(),
+        };
+        let raw = "%FLStudioFactoryData%/Data/kick.wav";
+        let resolved = resolve_sample_path(raw, Path::new("/"), &fl);
+        assert_eq!(resolved, Some(PathBuf::from("/opt/FL Studio 21/Data/kick.wav")));
+        let resolved = resolve_sample_path("%flstudiouserdata%/Audio/vox.wav", Path::new("/"), &fl);
+        assert_eq!(resolved, Some(PathBuf::from("/home/me/FL Studio/Audio/vox.wav")));
+    }
+
+    #[test]
+    fn test_literal_percent_signs_are_kept() {
+        let fl = FlFolders::default();
+        let resolved = resolve_sample_path("50% wet 100%.wav", Path::new("/s"), &fl);
+        assert_eq!(resolved, Some(PathBuf::from("/s/50% wet 100%.wav")));
+    }
+
+    #[test]
+    fn test_find_missing_samples() {
+        let dir = tempdir().unwrap();
+        let db = init_db(dir.path()).unwrap();
+        std::fs::write(dir.path().join("present.wav"), b"RIFF").unwrap();
+
+        let meta = FlpMetadata {
+            generators: vec![ChannelInfo {
+                name: "Drums".to_string(),
+                sample_paths: vec![
+                    "present.wav".to_string(),
+                    "gone.wav".to_string(),
+                    "%FLPV_NOT_SET_VAR%/x.wav".to_string(),
+                ],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let project = dir.path().join("song.flp").to_string_lossy().to_string();
+        store_project(&db, parsed("abc", &project, &meta));
+
+        let reports = find_missing_samples(&db, &FlFolders::default()).unwrap();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].missing.len(), 1);
+        assert_eq!(reports[0].missing[0].path, "gone.wav");
+        assert_eq!(reports[0].unresolved.len(), 1);
+    }
+
+    #[test]
+    fn test_bundled_samples_are_not_missing() {
+        let dir = tempdir().unwrap();
+        let db = init_db(dir.path()).unwrap();
+        let meta = FlpMetadata {
+            generators: vec![ChannelInfo {
+                name: "Drums".to_string(),
+                sample_paths: vec!["C:\\Samples\\Kick.wav".to_string()],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let project = dir.path().join("song.zip").to_string_lossy().to_string();
+        store_project(&db, parsed("abc", &project, &meta));
+        assert_eq!(find_missing_samples(&db, &FlFolders::default()).unwrap().len(), 1);
+
+        let bundled = vec!["Song/Samples/kick.wav".to_string()];
+        store_project(&db, ParsedFile { bundled, ..parsed("abc", &project, &meta) });
+        assert!(find_missing_samples(&db, &FlFolders::default()).unwrap().is_empty());
+    }
+}
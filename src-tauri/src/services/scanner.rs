@@ -1,11 +1,28 @@
 use crate::parser;
-use crate::store::files::{hash_in_cache, is_cached, update_path_index, upsert_file};
-use rusqlite::Connection;
+use crate::services::archive;
+use crate::parser::types::{ParseWarning, WarningCode};
+use crate::store::batch::{ParsedFile, ScanBatch, ScanWrite};
+use crate::store::files::{hash_in_cache, is_cached, FileKind};
+use crate::store::{Db, StoreError};
 use serde::Serialize;
-use std::sync::{Arc, Mutex};
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::collections::BTreeMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
 use tauri::{AppHandle, Emitter};
 use walkdir::WalkDir;
-use xxhash_rust::xxh3::xxh3_64;
+use xxhash_rust::xxh3::Xxh3;
+
+/// Read size when hashing bytes the parser skips.
+const HASH_CHUNK: usize = 64 * 1024;
+
+/// Scan results are committed together once this many are waiting...
+const BATCH_FILES: usize = 200;
+/// ...or the oldest has waited this long, so the library fills in steadily.
+const BATCH_AGE: Duration = Duration::from_millis(500);
 
 #[derive(Debug, Serialize, Clone)]
 struct ScanStarted {
@@ -17,7 +34,9 @@ struct ScanProgress {
     done: usize,
     total: usize,
     path: String,
-    warnings: Vec<String>,
+    /// Content hash, when the file was read this time.
+    hash: Option<String>,
+    warnings: Vec<ParseWarning>,
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -30,155 +49,683 @@ struct ScanCancelled {
     done: usize,
 }
 
+#[derive(Debug, Serialize, Clone)]
+struct ScanFailed {
+    done: usize,
+    /// File being scanned when the database failed; None if it failed while
+    /// committing the last results.
+    path: Option<String>,
+    message: String,
+}
+
+/// Hashes a file as it is read, so it can be parsed and hashed in one pass.
+/// Seeking forward reads and hashes the bytes skipped instead, and seeking
+/// back rereads bytes without hashing them twice, so `finish` gives the same
+/// value as `xxh3_64` over the whole file whatever the parser did.
+struct HashingReader<R> {
+    inner: R,
+    hasher: Xxh3,
+    pos: u64,
+    /// Everything before this offset has been hashed.
+    hashed: u64,
+}
+
+impl<R: Read + Seek> HashingReader<R> {
+    fn new(inner: R) -> Self {
+        HashingReader { inner, hasher: Xxh3::new(), pos: 0, hashed: 0 }
+    }
+
+    /// Read on from the hashed end up to `target`, or to the end of the file.
+    fn hash_through(&mut self, target: Option<u64>) -> io::Result<()> {
+        if self.pos != self.hashed {
+            self.pos = self.inner.seek(SeekFrom::Start(self.hashed))?;
+        }
+        let mut buf = vec![0u8; HASH_CHUNK];
+        loop {
+            let want = target.map_or(HASH_CHUNK as u64, |t| t.saturating_sub(self.pos));
+            let want = want.min(HASH_CHUNK as u64) as usize;
+            if want == 0 || self.read(&mut buf[..want])? == 0 {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Hash whatever the parser did not read and return the content hash.
+    fn finish(mut self) -> io::Result<String> {
+        self.hash_through(None)?;
+        Ok(format!("{:016x}", self.hasher.digest()))
+    }
+}
+
+impl<R: Read + Seek> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        let end = self.pos + n as u64;
+        if self.pos <= self.hashed && end > self.hashed {
+            self.hasher.update(&buf[(self.hashed - self.pos) as usize..n]);
+            self.hashed = end;
+        }
+        self.pos = end;
+        Ok(n)
+    }
+}
+
+impl<R: Read + Seek> Seek for HashingReader<R> {
+    fn seek(&mut self, from: SeekFrom) -> io::Result<u64> {
+        let target = match from {
+            SeekFrom::Start(t) => t,
+            SeekFrom::Current(d) => self.pos.checked_add_signed(d).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidInput, "Seek before the start of the file")
+            })?,
+            SeekFrom::End(_) => {
+                let t = self.inner.seek(from)?;
+                self.inner.seek(SeekFrom::Start(self.pos))?;
+                t
+            }
+        };
+        if target > self.hashed {
+            self.hash_through(Some(target))?;
+        }
+        if self.pos != target {
+            self.pos = self.inner.seek(SeekFrom::Start(target))?;
+        }
+        Ok(self.pos)
+    }
+}
+
+/// Folder FL Studio keeps overwritten copies of a project in.
+const BACKUP_DIR: &str = "backup";
+
+/// Tell FL Studio's own copies from user saves by its naming: "Song
+/// (autosave).flp", "Song (overwritten at 1432h05).flp", or anything inside a
+/// Backup folder.
+pub fn classify_file(path: &Path) -> FileKind {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    if name.contains("(autosave)") {
+        return FileKind::Autosave;
+    }
+    let in_backup_dir = path
+        .parent()
+        .and_then(|p| p.file_name())
+        .map(|d| d.to_string_lossy().eq_ignore_ascii_case(BACKUP_DIR))
+        .unwrap_or(false);
+    if name.contains("(overwritten at") || in_backup_dir {
+        FileKind::Backup
+    } else {
+        FileKind::User
+    }
+}
+
+/// Files that may be projects: .flp files, and zips. Only the name is looked
+/// at, so the walk stays fast; `scan_file` skips zips without a project.
+fn is_project_file(path: &Path) -> bool {
+    let is_flp = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.eq_ignore_ascii_case("flp"))
+        .unwrap_or(false);
+    is_flp || archive::is_zip(path)
+}
+
+/// Parse a project file read through `reader`. Zipped projects also return
+/// the other files in the archive.
+fn parse_project<R: Read + Seek>(
+    path: &Path,
+    reader: R,
+) -> Result<(parser::FlpMetadata, Vec<String>), String> {
+    if archive::is_zip(path) {
+        return archive::read_zip_project(reader).map(|p| (p.meta, p.bundled));
+    }
+    parser::parse_flp_reader(BufReader::new(reader))
+        .map(|meta| (meta, Vec::new()))
+        .map_err(|e| e.to_string())
+}
+
+/// What scanning one file produced: the results to store and what its
+/// progress event reports.
+struct FileOutcome {
+    /// Content hash, when the file was read this time.
+    hash: Option<String>,
+    warnings: Vec<ParseWarning>,
+    write: Option<ScanWrite>,
+}
+
+impl FileOutcome {
+    fn unreadable(message: String) -> Self {
+        FileOutcome {
+            hash: None,
+            warnings: vec![ParseWarning::new(WarningCode::Unreadable, message)],
+            write: None,
+        }
+    }
+
+    /// Left out of the library so the next scan tries it again.
+    fn crashed() -> Self {
+        FileOutcome {
+            hash: None,
+            warnings: vec![ParseWarning::new(
+                WarningCode::ParseFailed,
+                "Parse error: the parser crashed on this file",
+            )],
+            write: None,
+        }
+    }
+}
+
+/// Hash and parse one project unless the cache already has it. Only reads
+/// the database, so workers can run it side by side. Problems with the file
+/// become warnings; only database failures are errors.
+fn scan_file(db: &Db, path: &Path, file_path: &str) -> Result<FileOutcome, StoreError> {
+    let (file_size, mtime) = match path.metadata() {
+        Ok(m) => {
+            let size = m.len() as i64;
+            let mt = m
+                .modified()
+                .ok()
+                .and_then(|t| {
+                    t.duration_since(std::time::UNIX_EPOCH).ok()
+                })
+                .map(|d| d.as_secs() as i64)
+                .unwrap_or(0);
+            (size, mt)
+        }
+        Err(_) => return Ok(FileOutcome::unreadable("Failed to read file metadata".into())),
+    };
+
+    if is_cached(db, file_path, file_size, mtime)? {
+        return Ok(FileOutcome { hash: None, warnings: vec![], write: None });
+    }
+    // Sample packs and other zips are common in project folders
+    if archive::is_zip(path) && !archive::is_project_zip(path) {
+        return Ok(FileOutcome { hash: None, warnings: vec![], write: None });
+    }
+
+    let unreadable = |e: io::Error| FileOutcome::unreadable(format!("Failed to read file: {}", e));
+    let file = match File::open(path) {
+        Ok(f) => f,
+        Err(e) => return Ok(unreadable(e)),
+    };
+    // The file is read once: the parser's reads feed the hash. An identical
+    // copy already in the library is parsed for nothing, which costs less
+    // than reading every new file twice
+    let mut reader = HashingReader::new(file);
+    let parsed = parse_project(path, &mut reader);
+    let hash = match reader.finish() {
+        Ok(h) => h,
+        Err(e) => return Ok(unreadable(e)),
+    };
+
+    if hash_in_cache(db, &hash)? {
+        let write = ScanWrite::Path {
+            path: file_path.to_string(),
+            hash: hash.clone(),
+            file_size,
+            mtime,
+            kind: classify_file(path),
+        };
+        return Ok(FileOutcome { hash: Some(hash), warnings: vec![], write: Some(write) });
+    }
+
+    let (meta, bundled) = match parsed {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            let warning =
+                ParseWarning::new(WarningCode::ParseFailed, format!("Parse error: {}", e));
+            let empty_meta = crate::parser::types::FlpMetadata {
+                warnings: vec![warning],
+                ..Default::default()
+            };
+            (empty_meta, Vec::new())
+        }
+    };
+    let warnings = meta.warnings.clone();
+    let write = ScanWrite::File(Box::new(ParsedFile {
+        hash: hash.clone(),
+        path: file_path.to_string(),
+        file_size,
+        mtime,
+        kind: classify_file(path),
+        meta,
+        bundled,
+    }));
+    Ok(FileOutcome { hash: Some(hash), warnings, write: Some(write) })
+}
+
+/// Most worker threads a scan uses; past this the disk is the limit anyway.
+const MAX_WORKERS: usize = 64;
+
+/// Worker threads for a `scan_workers` setting; 0 means one per CPU.
+pub fn worker_count(setting: usize) -> usize {
+    let count = if setting > 0 {
+        setting
+    } else {
+        std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+    };
+    count.min(MAX_WORKERS)
+}
+
+/// How a scan of a file list ended.
+#[derive(Debug)]
+enum ScanEnd {
+    Complete,
+    Cancelled { done: usize },
+    Failed(ScanFailed),
+}
+
+/// Scan `files` with `workers` threads reading, hashing and parsing while
+/// this thread stores the results and reports progress in list order.
+/// Clearing `running` cancels the scan after the files already reported;
+/// those are kept so a rescan starts from there.
+fn scan_files(
+    db: &Db,
+    files: &[String],
+    workers: usize,
+    running: &Mutex<bool>,
+    mut on_progress: impl FnMut(ScanProgress),
+) -> ScanEnd {
+    let total = files.len();
+    let is_running = || *running.lock().unwrap();
+    // Bounds how far workers get ahead of the file being reported, so one
+    // slow file does not leave every other result waiting in memory
+    let window = workers.max(1) * 4;
+    let mut batch = ScanBatch::new(db, BATCH_FILES, BATCH_AGE);
+
+    let (job_tx, job_rx) = mpsc::channel::<usize>();
+    let job_rx = Mutex::new(job_rx);
+    let end = std::thread::scope(|scope| {
+        // Owned here so the queue closes, and idle workers exit, on return
+        let job_tx = job_tx;
+        let (result_tx, result_rx) = mpsc::channel();
+
+        for _ in 0..workers.max(1) {
+            let (job_rx, result_tx) = (&job_rx, result_tx.clone());
+            scope.spawn(move || loop {
+                // Release the queue before working so other workers can take jobs
+                let job = job_rx.lock().unwrap().recv();
+                let Ok(i) = job else { break };
+                // Jobs taken after a cancel are still answered, as None, so the
+                // reporting loop never waits on a file nobody will scan
+                let outcome = is_running().then(|| {
+                    let file_path = &files[i];
+                    // A parser bug must not take the worker down with the file's result
+                    panic::catch_unwind(AssertUnwindSafe(|| {
+                        scan_file(db, Path::new(file_path), file_path)
+                    }))
+                    .unwrap_or_else(|_| Ok(FileOutcome::crashed()))
+                });
+                if result_tx.send((i, outcome)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(result_tx);
+
+        let mut finished = BTreeMap::new();
+        let mut queued = 0;
+        for (done, file_path) in files.iter().enumerate() {
+            while queued < total && queued < done + window {
+                // The receiver outlives this scope, so this cannot fail
+                let _ = job_tx.send(queued);
+                queued += 1;
+            }
+            if !is_running() {
+                return ScanEnd::Cancelled { done };
+            }
+
+            let outcome = loop {
+                if let Some(outcome) = finished.remove(&done) {
+                    break outcome;
+                }
+                // Every job taken is answered, so this only fails if a worker died
+                let Ok((i, outcome)) = result_rx.recv() else {
+                    return ScanEnd::Cancelled { done };
+                };
+                finished.insert(i, outcome);
+            };
+            let Some(outcome) = outcome else {
+                return ScanEnd::Cancelled { done };
+            };
+
+            let file_path = file_path.clone();
+            // The database is shared by every file, so a failure there ends the scan
+            let stored = outcome.and_then(|o| match o.write {
+                Some(write) => batch.add(write).map(|_| (o.hash, o.warnings)),
+                None => Ok((o.hash, o.warnings)),
+            });
+            let (hash, warnings) = match stored {
+                Ok(reported) => reported,
+                Err(e) => {
+                    *running.lock().unwrap() = false;
+                    return ScanEnd::Failed(ScanFailed {
+                        done,
+                        path: Some(file_path),
+                        message: e.to_string(),
+                    });
+                }
+            };
+            on_progress(ScanProgress {
+                done: done + 1,
+                total,
+                path: file_path,
+                hash,
+                warnings,
+            });
+        }
+        ScanEnd::Complete
+    });
+
+    // Results reported before a failure are kept too, and the failure that
+    // ended the scan is the one reported even if this commit fails as well
+    let done = match &end {
+        ScanEnd::Complete => total,
+        ScanEnd::Cancelled { done } => *done,
+        ScanEnd::Failed(failed) => failed.done,
+    };
+    match (batch.flush(), end) {
+        (Err(e), ScanEnd::Complete | ScanEnd::Cancelled { .. }) => ScanEnd::Failed(ScanFailed {
+            done,
+            path: None,
+            message: e.to_string(),
+        }),
+        (_, end) => end,
+    }
+}
+
 pub fn run_scan(
     path: &str,
-    db: Arc<Mutex<Connection>>,
+    db: Arc<Db>,
     app: AppHandle,
     scan_running: Arc<Mutex<bool>>,
+    workers: usize,
 ) {
-    let flp_files: Vec<walkdir::DirEntry> = WalkDir::new(path)
+    let flp_files: Vec<String> = WalkDir::new(path)
         .follow_links(false)
         .into_iter()
         .filter_map(|e| e.ok())
-        .filter(|e| {
-            e.file_type().is_file()
-                && e.path()
-                    .extension()
-                    .and_then(|ext| ext.to_str())
-                    .map(|ext| ext.eq_ignore_ascii_case("flp"))
-                    .unwrap_or(false)
-        })
+        .filter(|e| e.file_type().is_file() && is_project_file(e.path()))
+        .map(|e| e.path().to_string_lossy().to_string())
         .collect();
 
     let total = flp_files.len();
 
     let _ = app.emit("scan:started", ScanStarted { total });
 
-    let mut done = 0usize;
+    let end = scan_files(&db, &flp_files, workers, &scan_running, |progress| {
+        let _ = app.emit("scan:progress", progress);
+    });
 
-    for entry in &flp_files {
-        {
-            let running = scan_running.lock().unwrap();
-            if !*running {
-                drop(running);
-                let _ = app.emit("scan:cancelled", ScanCancelled { done });
-                return;
-            }
-        }
+    *scan_running.lock().unwrap() = false;
+    let _ = match end {
+        ScanEnd::Complete => app.emit("scan:complete", ScanComplete { total }),
+        ScanEnd::Cancelled { done } => app.emit("scan:cancelled", ScanCancelled { done }),
+        ScanEnd::Failed(failed) => app.emit("scan:failed", failed),
+    };
+}
 
-        let file_path = entry.path().to_string_lossy().to_string();
-
-        let meta_result = entry.metadata();
-        let (file_size, mtime) = match meta_result {
-            Ok(m) => {
-                let size = m.len() as i64;
-                let mt = m
-                    .modified()
-                    .ok()
-                    .and_then(|t| {
-                        t.duration_since(std::time::UNIX_EPOCH).ok()
-                    })
-                    .map(|d| d.as_secs() as i64)
-                    .unwrap_or(0);
-                (size, mt)
-            }
-            Err(_) => {
-                done += 1;
-                let _ = app.emit(
-                    "scan:progress",
-                    ScanProgress {
-                        done,
-                        total,
-                        path: file_path,
-                        warnings: vec!["Failed to read file metadata".to_string()],
-                    },
-                );
-                continue;
-            }
-        };
 
-        if is_cached(&db, &file_path, file_size, mtime) {
-            done += 1;
-            let _ = app.emit(
-                "scan:progress",
-                ScanProgress {
-                    done,
-                    total,
-                    path: file_path,
-                    warnings: vec![],
-                },
-            );
-            continue;
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    use xxhash_rust::xxh3::xxh3_64;
 
-        let bytes = match std::fs::read(entry.path()) {
-            Ok(b) => b,
-            Err(e) => {
-                done += 1;
-                let _ = app.emit(
-                    "scan:progress",
-                    ScanProgress {
-                        done,
-                        total,
-                        path: file_path,
-                        warnings: vec![format!("Failed to read file: {}", e)],
-                    },
-                );
-                continue;
-            }
+    #[test]
+    fn test_classify_autosave_and_backup() {
+        let kind = |p: &str| classify_file(Path::new(p));
+        assert_eq!(kind("/music/Song.flp"), FileKind::User);
+        assert_eq!(kind("/music/Song (autosave).flp"), FileKind::Autosave);
+        assert_eq!(kind("/music/Backup/Song (autosave).flp"), FileKind::Autosave);
+        assert_eq!(kind("/music/Backup/Song (overwritten at 1432h05).flp"), FileKind::Backup);
+        assert_eq!(kind("/music/backup/Song 3.flp"), FileKind::Backup);
+        assert_eq!(kind("/music/Backups of mine/Song.flp"), FileKind::User);
+    }
+
+    #[test]
+    fn test_scan_file_caches_and_reports_store_errors() {
+        let dir = tempdir().unwrap();
+        let db = crate::store::init_db(&dir.path().join("data")).unwrap();
+        let mut batch = ScanBatch::new(&db, 1, BATCH_AGE);
+        let path = dir.path().join("Song.flp");
+        std::fs::write(&path, b"not an flp").unwrap();
+        let file_path = path.to_string_lossy().to_string();
+
+        let first = scan_file(&db, &path, &file_path).unwrap();
+        assert!(first.hash.is_some());
+        assert_eq!(first.warnings[0].code, WarningCode::ParseFailed);
+        batch.add(first.write.unwrap()).unwrap();
+        let second = scan_file(&db, &path, &file_path).unwrap();
+        assert!(second.hash.is_none(), "Unchanged file should come from the cache");
+
+        db.write().execute_batch("DROP TABLE path_index").unwrap();
+        assert!(matches!(
+            scan_file(&db, &path, &file_path),
+            Err(StoreError::Sqlite(_))
+        ));
+    }
+
+    #[test]
+    fn test_scan_file_skips_zip_without_project() {
+        let dir = tempdir().unwrap();
+        let db = crate::store::init_db(&dir.path().join("data")).unwrap();
+        let path = dir.path().join("Drums.zip");
+        std::fs::write(&path, b"not a zip").unwrap();
+        assert!(is_project_file(&path));
+
+        let outcome = scan_file(&db, &path, &path.to_string_lossy()).unwrap();
+        assert!(outcome.hash.is_none());
+        assert!(outcome.warnings.is_empty());
+        assert!(outcome.write.is_none());
+    }
+
+    #[test]
+    fn test_identical_autosave_and_user_save_count_as_user() {
+        let dir = tempdir().unwrap();
+        let db = crate::store::init_db(&dir.path().join("data")).unwrap();
+        let running = Mutex::new(true);
+        let path_of = |name: &str| {
+            let path = dir.path().join(name);
+            std::fs::write(&path, b"same bytes").unwrap();
+            path.to_string_lossy().to_string()
         };
+        let autosave = path_of("Song (autosave).flp");
+        let user = path_of("Song.flp");
+        let kind = || crate::store::list_all_files(&db).unwrap()[0].kind;
+
+        scan_files(&db, std::slice::from_ref(&autosave), 1, &running, |_| {});
+        assert_eq!(kind(), FileKind::Autosave);
+        // The user save is already in the library by hash, so only its path is written
+        scan_files(&db, &[autosave, user], 1, &running, |_| {});
+        let files = crate::store::list_all_files(&db).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(kind(), FileKind::User);
+        assert_eq!(serde_json::to_value(&files[0]).unwrap()["kind"], "user");
+    }
 
-        let hash = format!("{:016x}", xxh3_64(&bytes));
-
-        if hash_in_cache(&db, &hash) {
-            update_path_index(&db, &file_path, &hash, file_size, mtime);
-            done += 1;
-            let _ = app.emit(
-                "scan:progress",
-                ScanProgress {
-                    done,
-                    total,
-                    path: file_path,
-                    warnings: vec![],
-                },
-            );
-            continue;
+    #[test]
+    fn test_upgraded_library_is_parsed_again() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("Song.flp");
+        let mut flp = b"FLhd".to_vec();
+        flp.extend_from_slice(&[6, 0, 0, 0, 0, 0, 1, 0, 96, 0]);
+        flp.extend_from_slice(b"FLdt");
+        flp.extend_from_slice(&5u32.to_le_bytes());
+        flp.extend_from_slice(&[156, 0xe0, 0x22, 0x02, 0x00]);
+        std::fs::write(&path, &flp).unwrap();
+        let hash = format!("{:016x}", xxh3_64(&flp));
+
+        // A v1 library that already holds this file as "abc123"
+        let data = dir.path().join("data");
+        std::fs::create_dir_all(&data).unwrap();
+        let fixture = include_str!("../../tests/fixtures/schema_v1.sql").replace("abc123", &hash);
+        rusqlite::Connection::open(data.join("flp-vault.db"))
+            .unwrap()
+            .execute_batch(&fixture)
+            .unwrap();
+        let db = crate::store::init_db(&data).unwrap();
+
+        // The old path entry is kept but no longer counts as cached
+        assert!(!is_cached(&db, "/music/Song.flp", 2048, 1700000000).unwrap());
+
+        let file_path = path.to_string_lossy().to_string();
+        let outcome = scan_file(&db, &path, &file_path).unwrap();
+        assert!(matches!(outcome.write, Some(ScanWrite::File(_))), "Stale parse should be redone");
+        let mut batch = ScanBatch::new(&db, 1, BATCH_AGE);
+        batch.add(outcome.write.unwrap()).unwrap();
+
+        let files = crate::store::list_all_files(&db).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].ppq, Some(96));
+        assert_eq!(files[0].kind, FileKind::User);
+        assert_eq!(files[0].patterns_json.as_deref(), Some("[]"));
+        assert!(hash_in_cache(&db, &hash).unwrap());
+        assert!(is_cached(&db, &file_path, flp.len() as i64, files[0].mtime).unwrap());
+    }
+
+    /// Write `count` small unparseable projects into `dir`, listed in name order.
+    fn write_corpus(dir: &Path, count: usize) -> Vec<String> {
+        std::fs::create_dir_all(dir).unwrap();
+        (0..count)
+            .map(|i| {
+                let path = dir.join(format!("Song {:05}.flp", i));
+                // Uneven sizes so workers finish out of order
+                std::fs::write(&path, i.to_string().repeat(i % 7 * 500 + 1)).unwrap();
+                path.to_string_lossy().to_string()
+            })
+            .collect()
+    }
+
+    /// Scan every file into a fresh library, returning files per second.
+    fn scan_throughput(files: &[String], data: &Path, max_files: usize) -> f64 {
+        let db = crate::store::init_db(data).unwrap();
+        let mut batch = ScanBatch::new(&db, max_files, BATCH_AGE);
+
+        let start = std::time::Instant::now();
+        for file_path in files {
+            let outcome = scan_file(&db, Path::new(file_path), file_path).unwrap();
+            batch.add(outcome.write.unwrap()).unwrap();
         }
+        batch.flush().unwrap();
+        let rate = files.len() as f64 / start.elapsed().as_secs_f64();
 
-        let warnings = match parser::parse_flp(&bytes) {
-            Ok(meta) => {
-                let w = meta.warnings.clone();
-                upsert_file(&db, &hash, &file_path, file_size, mtime, &meta);
-                w
-            }
-            Err(e) => {
-                let warning = format!("Parse error: {:?}", e);
-                let empty_meta = crate::parser::types::FlpMetadata {
-                    warnings: vec![warning.clone()],
-                    ..Default::default()
-                };
-                upsert_file(&db, &hash, &file_path, file_size, mtime, &empty_meta);
-                vec![warning]
-            }
-        };
+        assert_eq!(crate::store::list_all_files(&db).unwrap().len(), files.len());
+        rate
+    }
 
-        done += 1;
-        let _ = app.emit(
-            "scan:progress",
-            ScanProgress {
-                done,
-                total,
-                path: file_path,
-                warnings,
-            },
+    /// Timings depend on the disk and machine, so this only reports them.
+    #[test]
+    #[ignore = "benchmark: cargo test batched_scan_throughput -- --ignored --nocapture"]
+    fn test_batched_scan_throughput() {
+        let dir = tempdir().unwrap();
+        let files = write_corpus(&dir.path().join("corpus"), 10_000);
+
+        let batched = scan_throughput(&files, &dir.path().join("batched"), BATCH_FILES);
+        let per_file = scan_throughput(&files, &dir.path().join("per-file"), 1);
+        println!(
+            "10k files: {:.0} files/s batched, {:.0} files/s committing each file",
+            batched, per_file
         );
     }
 
-    {
-        let mut running = scan_running.lock().unwrap();
-        *running = false;
+    #[test]
+    fn test_parallel_scan_reports_in_order() {
+        let dir = tempdir().unwrap();
+        let files = write_corpus(&dir.path().join("corpus"), 300);
+        let db = crate::store::init_db(&dir.path().join("data")).unwrap();
+        let running = Mutex::new(true);
+
+        let mut reported = Vec::new();
+        let end = scan_files(&db, &files, 4, &running, |progress| {
+            assert_eq!(progress.done, reported.len() + 1);
+            reported.push(progress.path);
+        });
+        assert!(matches!(end, ScanEnd::Complete));
+        assert_eq!(reported, files);
+        assert_eq!(crate::store::list_all_files(&db).unwrap().len(), files.len());
     }
 
-    let _ = app.emit("scan:complete", ScanComplete { total });
-}
+    #[test]
+    fn test_cancelled_scan_keeps_reported_files() {
+        let dir = tempdir().unwrap();
+        let files = write_corpus(&dir.path().join("corpus"), 100);
+        let db = crate::store::init_db(&dir.path().join("data")).unwrap();
+        let running = Mutex::new(true);
 
+        let end = scan_files(&db, &files, 4, &running, |progress| {
+            if progress.done == 10 {
+                *running.lock().unwrap() = false;
+            }
+        });
+        assert!(matches!(end, ScanEnd::Cancelled { done: 10 }));
+        // Files parsed ahead of the cancel are dropped, not stored
+        assert_eq!(crate::store::list_all_files(&db).unwrap().len(), 10);
+    }
+
+    #[test]
+    fn test_cancel_near_the_end_does_not_hang() {
+        let dir = tempdir().unwrap();
+        // Fewer files than workers, so most workers sit waiting for jobs
+        let files = write_corpus(&dir.path().join("corpus"), 3);
+        let db = Arc::new(crate::store::init_db(&dir.path().join("data")).unwrap());
+
+        for attempt in 0..200u64 {
+            let running = Arc::new(Mutex::new(true));
+            let (end_tx, end_rx) = mpsc::channel();
+            let (db, files, scan_running) = (db.clone(), files.clone(), running.clone());
+            std::thread::spawn(move || {
+                let end = scan_files(&db, &files, 8, &scan_running, |_| {});
+                let _ = end_tx.send(end);
+            });
+            // Vary when the cancel lands relative to the workers taking jobs
+            std::thread::sleep(Duration::from_micros(attempt % 20 * 10));
+            *running.lock().unwrap() = false;
+            let end = end_rx.recv_timeout(Duration::from_secs(10)).expect("Scan hung");
+            assert!(matches!(end, ScanEnd::Complete | ScanEnd::Cancelled { .. }));
+        }
+    }
+
+    #[test]
+    fn test_worker_count_is_capped() {
+        assert_eq!(worker_count(3), 3);
+        assert_eq!(worker_count(10_000), MAX_WORKERS);
+        assert!((1..=MAX_WORKERS).contains(&worker_count(0)));
+    }
+
+    #[test]
+    fn test_zip_project_is_hashed_while_parsed() {
+        use std::io::Write;
+        use zip::write::SimpleFileOptions;
+        let dir = tempdir().unwrap();
+        let db = crate::store::init_db(&dir.path().join("data")).unwrap();
+        let path = dir.path().join("Song.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&path).unwrap());
+        zip.start_file("Song/Samples/kick.wav", SimpleFileOptions::default()).unwrap();
+        zip.write_all(&[7u8; 200_000]).unwrap();
+        zip.start_file("Song/Song.flp", SimpleFileOptions::default()).unwrap();
+        zip.write_all(b"not an flp").unwrap();
+        zip.finish().unwrap();
+
+        let outcome = scan_file(&db, &path, &path.to_string_lossy()).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(outcome.hash, Some(format!("{:016x}", xxh3_64(&bytes))));
+    }
+
+    #[test]
+    fn test_hashing_reader_matches_one_shot_hash() {
+        // Spans several chunks and ends mid-chunk
+        let bytes: Vec<u8> = (0..HASH_CHUNK * 3 + 123).map(|i| (i % 251) as u8).collect();
+        let mut reader = HashingReader::new(io::Cursor::new(&bytes));
+        let mut buf = [0u8; 100];
+        reader.read_exact(&mut buf[..10]).unwrap();
+        // Skipped bytes are hashed, reread ones are not hashed again
+        reader.seek(SeekFrom::Current(HASH_CHUNK as i64 * 2)).unwrap();
+        reader.seek(SeekFrom::Start(5)).unwrap();
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf[0], 5);
+        assert_eq!(reader.seek(SeekFrom::End(0)).unwrap(), bytes.len() as u64);
+        reader.seek(SeekFrom::Start(3)).unwrap();
+        assert_eq!(reader.finish().unwrap(), format!("{:016x}", xxh3_64(&bytes)));
+    }
+}
@@ -1,4 +1,4 @@
-use rusqlite::Connection;
+use crate::store::Db;
 use std::sync::{Arc, Mutex};
 
 pub struct ScanStatus {
@@ -18,14 +18,14 @@ impl ScanStatus {
 }
 
 pub struct AppState {
-    pub db: Arc<Mutex<Connection>>,
+    pub db: Arc<Db>,
     pub scan_status: Mutex<ScanStatus>,
 }
 
 impl AppState {
-    pub fn new(db: Connection) -> Self {
+    pub fn new(db: Db) -> Self {
         Self {
-            db: Arc::new(Mutex::new(db)),
+            db: Arc::new(db),
             scan_status: Mutex::new(ScanStatus::new()),
         }
     }
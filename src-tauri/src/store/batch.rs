@@ -0,0 +1,171 @@
+use crate::parser::types::FlpMetadata;
+use crate::store::bundles::write_bundled_files;
+use crate::store::connection::Db;
+use crate::store::error::StoreError;
+use crate::store::files::{write_file, write_path_index, FileKind};
+use std::time::{Duration, Instant};
+
+/// A newly parsed project waiting to be written.
+#[derive(Debug, Clone)]
+pub struct ParsedFile {
+    pub hash: String,
+    pub path: String,
+    pub file_size: i64,
+    pub mtime: i64,
+    pub kind: FileKind,
+    pub meta: FlpMetadata,
+    /// Entries shipped next to the project inside a zip.
+    pub bundled: Vec<String>,
+}
+
+/// One scanned file's results.
+#[derive(Debug)]
+pub enum ScanWrite {
+    /// A path whose content is already in the library.
+    Path {
+        path: String,
+        hash: String,
+        file_size: i64,
+        mtime: i64,
+        kind: FileKind,
+    },
+    File(Box<ParsedFile>),
+}
+
+/// Collects scan results and writes them in one transaction once `max_files`
+/// are waiting or the oldest has waited `max_age`. Committing per file costs
+/// a WAL append and sync each time, which dominates on slow or encrypted
+/// mounts.
+///
+/// The age is checked when a result is added, so a long pause in the scan
+/// does not commit on its own. Call `flush` before reporting the scan as
+/// finished or cancelled; anything still pending when the batch is dropped is
+/// lost.
+pub struct ScanBatch<'a> {
+    db: &'a Db,
+    pending: Vec<ScanWrite>,
+    oldest: Option<Instant>,
+    max_files: usize,
+    max_age: Duration,
+}
+
+impl<'a> ScanBatch<'a> {
+    pub fn new(db: &'a Db, max_files: usize, max_age: Duration) -> Self {
+        Self {
+            db,
+            pending: Vec::new(),
+            oldest: None,
+            max_files: max_files.max(1),
+            max_age,
+        }
+    }
+
+    /// Queue one result, committing the batch if it is full or old enough.
+    pub fn add(&mut self, write: ScanWrite) -> Result<(), StoreError> {
+        self.pending.push(write);
+        let oldest = *self.oldest.get_or_insert_with(Instant::now);
+        if self.pending.len() >= self.max_files || oldest.elapsed() >= self.max_age {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Commit everything queued. On error the transaction is rolled back and
+    /// the queued results are discarded.
+    pub fn flush(&mut self) -> Result<(), StoreError> {
+        self.oldest = None;
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let pending = std::mem::take(&mut self.pending);
+
+        let conn = self.db.write();
+        let tx = conn.unchecked_transaction()?;
+        for write in &pending {
+            match write {
+                ScanWrite::Path { path, hash, file_size, mtime, kind } => {
+                    write_path_index(&tx, path, hash, *file_size, *mtime, *kind)?;
+                }
+                ScanWrite::File(file) => {
+                    let ParsedFile { hash, path, file_size, mtime, kind, .. } = file.as_ref();
+                    write_file(&tx, hash, path, *file_size, *mtime, *kind, &file.meta)?;
+                    write_bundled_files(&tx, hash, &file.bundled)?;
+                }
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::connection::init_db;
+    use crate::store::files::{is_cached, list_all_files};
+
+    fn parsed(n: usize) -> ScanWrite {
+        ScanWrite::File(Box::new(ParsedFile {
+            hash: format!("hash{}", n),
+            path: format!("/music/Song {}.flp", n),
+            file_size: 10,
+            mtime: 1,
+            kind: FileKind::User,
+            meta: FlpMetadata::default(),
+            bundled: vec![],
+        }))
+    }
+
+    #[test]
+    fn test_batch_commits_every_max_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = init_db(dir.path()).unwrap();
+        let mut batch = ScanBatch::new(&db, 3, Duration::from_secs(3600));
+
+        batch.add(parsed(0)).unwrap();
+        batch.add(parsed(1)).unwrap();
+        assert_eq!(batch.pending.len(), 2);
+        assert!(list_all_files(&db).unwrap().is_empty(), "Nothing is visible before a commit");
+
+        let copy = ScanWrite::Path {
+            path: "/music/copy.flp".to_string(),
+            hash: "hash0".to_string(),
+            file_size: 10,
+            mtime: 1,
+            kind: FileKind::User,
+        };
+        batch.add(copy).unwrap();
+        assert!(batch.pending.is_empty());
+        assert_eq!(list_all_files(&db).unwrap().len(), 2);
+        assert!(is_cached(&db, "/music/copy.flp", 10, 1).unwrap());
+
+        batch.add(parsed(2)).unwrap();
+        batch.flush().unwrap();
+        assert_eq!(list_all_files(&db).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_batch_commits_when_oldest_is_too_old() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = init_db(dir.path()).unwrap();
+        let mut batch = ScanBatch::new(&db, 1000, Duration::ZERO);
+
+        batch.add(parsed(0)).unwrap();
+        assert!(batch.pending.is_empty());
+        assert_eq!(list_all_files(&db).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_failed_flush_writes_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = init_db(dir.path()).unwrap();
+        let mut batch = ScanBatch::new(&db, 1000, Duration::from_secs(3600));
+        batch.add(parsed(0)).unwrap();
+        batch.add(parsed(1)).unwrap();
+
+        db.write().execute_batch("DROP TABLE bundled_files").unwrap();
+        assert!(batch.flush().is_err());
+        assert!(batch.pending.is_empty());
+        assert!(list_all_files(&db).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,42 @@
+use crate::store::connection::Db;
+use crate::store::error::StoreError;
+use rusqlite::Connection;
+use std::collections::{HashMap, HashSet};
+
+/// File name of an archive entry or sample path, lowercased for matching.
+/// FL Studio records Windows paths, so both separators are accepted.
+pub fn bundled_name(path: &str) -> String {
+    path.rsplit(['\\', '/']).next().unwrap_or(path).to_lowercase()
+}
+
+/// Replace the files recorded as bundled alongside a zipped project.
+pub(crate) fn write_bundled_files(
+    conn: &Connection,
+    hash: &str,
+    paths: &[String],
+) -> rusqlite::Result<()> {
+    conn.execute("DELETE FROM bundled_files WHERE hash = ?1", [hash])?;
+    let mut stmt =
+        conn.prepare("INSERT OR IGNORE INTO bundled_files (hash, path) VALUES (?1, ?2)")?;
+    for path in paths {
+        stmt.execute(rusqlite::params![hash, path])?;
+    }
+    Ok(())
+}
+
+/// Lowercased file names of the bundled files, per project hash.
+pub fn list_bundled_names(
+    db: &Db,
+) -> Result<HashMap<String, HashSet<String>>, StoreError> {
+    let conn = db.read();
+    let mut stmt = conn.prepare("SELECT hash, path FROM bundled_files")?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+
+    let mut names: HashMap<String, HashSet<String>> = HashMap::new();
+    for row in rows {
+        let (hash, path) = row?;
+        names.entry(hash).or_default().insert(bundled_name(&path));
+    }
+    Ok(names)
+}
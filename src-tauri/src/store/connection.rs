@@ -1,14 +1,81 @@
-use crate::store::migrations::run_migrations;
-use rusqlite::Connection;
+use crate::store::error::StoreError;
+use crate::store::migrations::{backup_before_migrating, run_migrations};
+use rusqlite::{Connection, OpenFlags};
+use std::ops::Deref;
 use std::path::Path;
-use std::sync::Mutex;
+use std::sync::{Condvar, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
 
-pub fn init_db(app_data_dir: &Path) -> Result<Mutex<Connection>, Box<dyn std::error::Error>> {
+/// How long a write waits for another connection's lock before failing with
+/// `StoreError::Locked`.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Read-only connections kept open next to the writer.
+const READERS: usize = 4;
+
+/// Shared handle to the library database: one connection for writes and a
+/// few read-only ones. In WAL mode readers see the last committed state while
+/// a write is in progress, so browsing never waits for a scan.
+pub struct Db {
+    writer: Mutex<Connection>,
+    readers: Mutex<Vec<Connection>>,
+    reader_returned: Condvar,
+}
+
+/// A read-only connection borrowed from the pool, returned when dropped.
+pub struct ReadConn<'a> {
+    db: &'a Db,
+    conn: Option<Connection>,
+}
+
+impl Deref for ReadConn<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.conn.as_ref().expect("connection is held until drop")
+    }
+}
+
+impl Drop for ReadConn<'_> {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.take() {
+            self.db.idle_readers().push(conn);
+            self.db.reader_returned.notify_one();
+        }
+    }
+}
+
+impl Db {
+    /// The write connection. Writes are serialized here; a panic while it was
+    /// held leaves SQLite itself consistent (an open transaction rolls back
+    /// when dropped), so the poison flag is ignored.
+    pub fn write(&self) -> MutexGuard<'_, Connection> {
+        self.writer.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// A read-only connection, waiting for one to come free if all are busy.
+    pub fn read(&self) -> ReadConn<'_> {
+        let mut idle = self.idle_readers();
+        loop {
+            if let Some(conn) = idle.pop() {
+                return ReadConn { db: self, conn: Some(conn) };
+            }
+            idle = self.reader_returned.wait(idle).unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+
+    fn idle_readers(&self) -> MutexGuard<'_, Vec<Connection>> {
+        self.readers.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+pub fn init_db(app_data_dir: &Path) -> Result<Db, StoreError> {
     // CRITICAL: Create the directory before opening the DB — Tauri does not do this automatically
     std::fs::create_dir_all(app_data_dir)?;
 
     let db_path = app_data_dir.join("flp-vault.db");
     let conn = Connection::open(&db_path)?;
+    conn.busy_timeout(BUSY_TIMEOUT)?;
 
     // Performance and safety pragmas
     conn.execute_batch(
@@ -19,9 +86,27 @@ pub fn init_db(app_data_dir: &Path) -> Result<Mutex<Connection>, Box<dyn std::er
         ",
     )?;
 
+    // Keep a copy of the old schema in case an upgrade goes wrong
+    backup_before_migrating(&conn, &db_path)?;
     run_migrations(&conn)?;
 
-    Ok(Mutex::new(conn))
+    // Opened after migrating so they never see an old schema
+    let readers = (0..READERS)
+        .map(|_| {
+            let reader = Connection::open_with_flags(
+                &db_path,
+                OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+            )?;
+            reader.busy_timeout(BUSY_TIMEOUT)?;
+            Ok(reader)
+        })
+        .collect::<Result<Vec<_>, StoreError>>()?;
+
+    Ok(Db {
+        writer: Mutex::new(conn),
+        readers: Mutex::new(readers),
+        reader_returned: Condvar::new(),
+    })
 }
 
 #[cfg(test)]
@@ -32,11 +117,11 @@ mod tests {
     #[test]
     fn test_init_db_creates_tables() {
         let dir = tempfile::tempdir().unwrap();
-        let db_mutex = init_db(dir.path()).unwrap();
-        let db = db_mutex.lock().unwrap();
+        let db = init_db(dir.path()).unwrap();
+        let conn = db.read();
 
         let tables: Vec<String> = {
-            let mut stmt = db
+            let mut stmt = conn
                 .prepare(
                     "SELECT name FROM sqlite_master WHERE type='table' ORDER BY name",
                 )
@@ -61,8 +146,8 @@ mod tests {
     #[test]
     fn test_settings_defaults() {
         let dir = tempfile::tempdir().unwrap();
-        let db_mutex = init_db(dir.path()).unwrap();
-        let settings = get_all_settings(&db_mutex);
+        let db = init_db(dir.path()).unwrap();
+        let settings = get_all_settings(&db).unwrap();
 
         assert!(
             !settings.source_folder.is_empty(),
@@ -81,11 +166,33 @@ mod tests {
     #[test]
     fn test_set_and_get_setting() {
         let dir = tempfile::tempdir().unwrap();
-        let db_mutex = init_db(dir.path()).unwrap();
+        let db = init_db(dir.path()).unwrap();
 
-        set_setting(&db_mutex, "test_key", "test_value");
-        let result = get_setting(&db_mutex, "test_key");
+        set_setting(&db, "test_key", "test_value").unwrap();
+        let result = get_setting(&db, "test_key").unwrap();
 
         assert_eq!(result, Some("test_value".to_string()));
     }
+
+    #[test]
+    fn test_reads_do_not_wait_for_an_open_write() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = init_db(dir.path()).unwrap();
+        set_setting(&db, "theme", "dark").unwrap();
+
+        let writer = db.write();
+        writer
+            .execute_batch("BEGIN; UPDATE settings SET value = 'light' WHERE key = 'theme';")
+            .unwrap();
+
+        // Another thread reads while the write transaction is still open
+        let seen = std::thread::scope(|s| {
+            s.spawn(|| get_setting(&db, "theme").unwrap()).join().unwrap()
+        });
+        assert_eq!(seen, Some("dark".to_string()), "Uncommitted writes must not be visible");
+
+        writer.execute_batch("COMMIT").unwrap();
+        drop(writer);
+        assert_eq!(get_setting(&db, "theme").unwrap(), Some("light".to_string()));
+    }
 }
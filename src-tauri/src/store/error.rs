@@ -0,0 +1,115 @@
+use rusqlite::ErrorCode;
+use std::fmt;
+
+/// Why a library database operation failed, grouped by what the user can do
+/// about it. `Display` gives a message fit to show in the UI.
+#[derive(Debug)]
+pub enum StoreError {
+    /// Another process holds a lock on the database file.
+    Locked,
+    /// A write broke a uniqueness or foreign-key rule.
+    Constraint(String),
+    /// The file is damaged or not a SQLite database.
+    Corrupt(String),
+    /// The file or its folder could not be read or written.
+    Io(String),
+    /// The database was upgraded by a newer release.
+    TooNew { version: u32, supported: u32 },
+    /// Any other SQLite failure, usually a bug in a query.
+    Sqlite(String),
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::Locked => write!(
+                f,
+                "The library database is in use by another program. Close other copies of \
+                 FLP Vault and try again."
+            ),
+            StoreError::Constraint(msg) => {
+                write!(f, "The library database rejected a change: {}", msg)
+            }
+            StoreError::Corrupt(msg) => write!(
+                f,
+                "The library database is damaged ({}). Restore a flp-vault.db.v*.bak backup, \
+                 or delete flp-vault.db and rescan to rebuild it.",
+                msg
+            ),
+            StoreError::Io(msg) => write!(
+                f,
+                "Could not read or write the library database ({}). Check free disk space \
+                 and permissions on the app data folder.",
+                msg
+            ),
+            StoreError::TooNew { version, supported } => write!(
+                f,
+                "The library database was upgraded by a newer FLP Vault (schema v{}, this \
+                 version supports v{}). Update FLP Vault to open it.",
+                version, supported
+            ),
+            StoreError::Sqlite(msg) => write!(f, "Database error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {}
+
+impl From<rusqlite::Error> for StoreError {
+    fn from(e: rusqlite::Error) -> Self {
+        let code = match &e {
+            rusqlite::Error::SqliteFailure(err, _) => err.code,
+            _ => return StoreError::Sqlite(e.to_string()),
+        };
+        match code {
+            ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked => StoreError::Locked,
+            ErrorCode::ConstraintViolation => StoreError::Constraint(e.to_string()),
+            ErrorCode::DatabaseCorrupt | ErrorCode::NotADatabase => {
+                StoreError::Corrupt(e.to_string())
+            }
+            ErrorCode::SystemIoFailure
+            | ErrorCode::DiskFull
+            | ErrorCode::CannotOpen
+            | ErrorCode::ReadOnly
+            | ErrorCode::PermissionDenied
+            | ErrorCode::FileLockingProtocolFailed => StoreError::Io(e.to_string()),
+            _ => StoreError::Sqlite(e.to_string()),
+        }
+    }
+}
+
+impl From<std::io::Error> for StoreError {
+    fn from(e: std::io::Error) -> Self {
+        StoreError::Io(e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::Connection;
+
+    #[test]
+    fn test_sqlite_errors_are_classified() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("CREATE TABLE t (id INTEGER PRIMARY KEY)").unwrap();
+        conn.execute("INSERT INTO t (id) VALUES (1)", []).unwrap();
+
+        let duplicate = conn.execute("INSERT INTO t (id) VALUES (1)", []).unwrap_err();
+        assert!(matches!(StoreError::from(duplicate), StoreError::Constraint(_)));
+        let bad_sql = conn.execute("SELECT FROM", []).unwrap_err();
+        assert!(matches!(StoreError::from(bad_sql), StoreError::Sqlite(_)));
+    }
+
+    #[test]
+    fn test_non_database_file_is_corrupt() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("flp-vault.db");
+        std::fs::write(&path, vec![0xAB; 4096]).unwrap();
+        let conn = Connection::open(&path).unwrap();
+        let err = conn.query_row("PRAGMA user_version", [], |row| row.get::<_, u32>(0));
+        let err = StoreError::from(err.unwrap_err());
+        assert!(matches!(err, StoreError::Corrupt(_)));
+        assert!(err.to_string().contains("rescan"));
+    }
+}
@@ -1,7 +1,41 @@
 use crate::parser::types::FlpMetadata;
-use rusqlite::Connection;
+use crate::parser::PARSER_VERSION;
+use crate::store::connection::Db;
+use crate::store::error::StoreError;
+use crate::store::plugins::replace_plugin_refs;
+use crate::store::samples::replace_samples;
+use crate::store::unknown_events::replace_unknown_events;
+use crate::store::warnings::replace_warnings;
+use rusqlite::{Connection, OptionalExtension};
 use serde::Serialize;
-use std::sync::Mutex;
+
+/// Who wrote a project file: the user, or FL Studio's autosave/backup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileKind {
+    User,
+    Autosave,
+    Backup,
+}
+
+impl FileKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FileKind::User => "user",
+            FileKind::Autosave => "autosave",
+            FileKind::Backup => "backup",
+        }
+    }
+
+    /// Unknown values are treated as user saves.
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "autosave" => FileKind::Autosave,
+            "backup" => FileKind::Backup,
+            _ => FileKind::User,
+        }
+    }
+}
 
 #[derive(Debug, Clone, Serialize)]
 pub struct FileRecord {
@@ -9,67 +43,133 @@ pub struct FileRecord {
     pub path: String,
     pub file_size: i64,
     pub mtime: i64,
+    pub kind: FileKind,
+    pub header_format: Option<i64>,
+    pub ppq: Option<i64>,
     pub bpm: Option<f64>,
+    pub time_sig_num: Option<i64>,
+    pub time_sig_den: Option<i64>,
     pub channel_count: Option<i64>,
+    pub song_length_bars: Option<f64>,
+    pub song_length_secs: Option<f64>,
     pub plugins_json: Option<String>,
+    pub patterns_json: Option<String>,
+    pub automation_json: Option<String>,
+    pub routing_json: Option<String>,
+    pub warnings_json: Option<String>,
     pub fl_version: Option<String>,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub genre: Option<String>,
+    pub comments: Option<String>,
+    pub url: Option<String>,
+    pub created_at: Option<i64>,
+    pub time_spent_secs: Option<i64>,
 }
 
-pub fn is_cached(db: &Mutex<Connection>, path: &str, file_size: i64, mtime: i64) -> bool {
-    let conn = db.lock().unwrap();
-    conn.query_row(
-        "SELECT 1 FROM path_index WHERE path = ?1 AND file_size = ?2 AND mtime = ?3",
-        [path, &file_size.to_string(), &mtime.to_string()],
-        |_| Ok(true),
-    )
-    .unwrap_or(false)
+pub fn is_cached(
+    db: &Db,
+    path: &str,
+    file_size: i64,
+    mtime: i64,
+) -> Result<bool, StoreError> {
+    let conn = db.read();
+    let found = conn
+        .query_row(
+            "SELECT 1 FROM path_index p JOIN files f ON f.hash = p.hash
+             WHERE p.path = ?1 AND p.file_size = ?2 AND p.mtime = ?3
+               AND f.parser_version = ?4",
+            rusqlite::params![path, file_size, mtime, PARSER_VERSION],
+            |_| Ok(true),
+        )
+        .optional()?;
+    Ok(found.is_some())
 }
 
-pub fn hash_in_cache(db: &Mutex<Connection>, hash: &str) -> bool {
-    let conn = db.lock().unwrap();
-    conn.query_row(
-        "SELECT 1 FROM files WHERE hash = ?1",
-        [hash],
-        |_| Ok(true),
-    )
-    .unwrap_or(false)
+/// Whether `hash` was parsed by this version of the parser. Projects stored
+/// by an older one are parsed again.
+pub fn hash_in_cache(db: &Db, hash: &str) -> Result<bool, StoreError> {
+    let conn = db.read();
+    let found = conn
+        .query_row(
+            "SELECT 1 FROM files WHERE hash = ?1 AND parser_version = ?2",
+            rusqlite::params![hash, PARSER_VERSION],
+            |_| Ok(true),
+        )
+        .optional()?;
+    Ok(found.is_some())
 }
 
-pub fn update_path_index(
-    db: &Mutex<Connection>,
+/// Record that `path` currently holds the content `hash`, written as `kind`.
+pub(crate) fn write_path_index(
+    conn: &Connection,
     path: &str,
     hash: &str,
     file_size: i64,
     mtime: i64,
-) {
-    let conn = db.lock().unwrap();
+    kind: FileKind,
+) -> rusqlite::Result<()> {
+    let previous: Option<String> = conn
+        .query_row("SELECT hash FROM path_index WHERE path = ?1", [path], |row| row.get(0))
+        .optional()?;
     conn.execute(
-        "INSERT INTO path_index (path, hash, file_size, mtime) VALUES (?1, ?2, ?3, ?4)
-         ON CONFLICT(path) DO UPDATE SET hash = excluded.hash, file_size = excluded.file_size, mtime = excluded.mtime",
-        rusqlite::params![path, hash, file_size, mtime],
-    )
-    .unwrap();
+        "INSERT INTO path_index (path, hash, file_size, mtime, kind) VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(path) DO UPDATE SET hash = excluded.hash, file_size = excluded.file_size,
+            mtime = excluded.mtime, kind = excluded.kind",
+        rusqlite::params![path, hash, file_size, mtime, kind.as_str()],
+    )?;
+    refresh_file_kind(conn, hash)?;
+    match previous {
+        Some(previous) if previous != hash => refresh_file_kind(conn, &previous),
+        _ => Ok(()),
+    }
 }
 
-pub fn upsert_file(
-    db: &Mutex<Connection>,
+/// Identical files share one row, so a project counts as the user's own if
+/// any path holding it is a user save, then as a backup, then as an autosave.
+fn refresh_file_kind(conn: &Connection, hash: &str) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE files SET kind = COALESCE(
+            (SELECT kind FROM path_index WHERE hash = ?1
+             ORDER BY CASE kind WHEN 'user' THEN 0 WHEN 'backup' THEN 1 ELSE 2 END
+             LIMIT 1),
+            kind)
+         WHERE hash = ?1",
+        [hash],
+    )?;
+    Ok(())
+}
+
+/// Store a parsed project, its child rows and its path index entry.
+pub(crate) fn write_file(
+    conn: &Connection,
     hash: &str,
     path: &str,
     file_size: i64,
     mtime: i64,
+    kind: FileKind,
     meta: &FlpMetadata,
-) {
+) -> rusqlite::Result<()> {
+    // Identity names, so renaming a plugin in FL Studio doesn't change the list
     let plugins_json = {
-        let generators: Vec<String> = meta
+        let mut all_plugins: Vec<String> = meta
             .generators
             .iter()
-            .filter_map(|g| g.plugin_name.clone().or_else(|| Some(g.name.clone())))
+            .filter_map(|g| g.plugin.as_ref().map(|p| p.internal_name.clone()))
             .collect();
-        let mut all_plugins = generators;
         all_plugins.extend(meta.effects.clone());
         serde_json::to_string(&all_plugins).unwrap_or_else(|_| "[]".to_string())
     };
 
+    let patterns_json =
+        serde_json::to_string(&meta.patterns).unwrap_or_else(|_| "[]".to_string());
+
+    let automation_json =
+        serde_json::to_string(&meta.automation).unwrap_or_else(|_| "[]".to_string());
+
+    let routing_json =
+        serde_json::to_string(&meta.routing).unwrap_or_else(|_| "{}".to_string());
+
     let warnings_json =
         serde_json::to_string(&meta.warnings).unwrap_or_else(|_| "[]".to_string());
 
@@ -78,74 +178,127 @@ pub fn upsert_file(
         .unwrap_or_default()
         .as_secs() as i64;
 
-    {
-        let conn = db.lock().unwrap();
-        conn.execute(
-            "INSERT INTO files (hash, path, file_size, mtime, bpm, time_sig_num, time_sig_den,
-                                channel_count, pattern_count, mixer_track_count, plugins_json,
-                                warnings_json, fl_version, parsed_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)
-             ON CONFLICT(hash) DO UPDATE SET
-                path = excluded.path,
-                file_size = excluded.file_size,
-                mtime = excluded.mtime,
-                bpm = excluded.bpm,
-                time_sig_num = excluded.time_sig_num,
-                time_sig_den = excluded.time_sig_den,
-                channel_count = excluded.channel_count,
-                pattern_count = excluded.pattern_count,
-                mixer_track_count = excluded.mixer_track_count,
-                plugins_json = excluded.plugins_json,
-                warnings_json = excluded.warnings_json,
-                fl_version = excluded.fl_version,
-                parsed_at = excluded.parsed_at",
-            rusqlite::params![
-                hash,
-                path,
-                file_size,
-                mtime,
-                meta.bpm.map(|b| b as f64),
-                meta.time_sig_num.map(|n| n as i64),
-                meta.time_sig_den.map(|d| d as i64),
-                meta.channel_count as i64,
-                meta.pattern_count as i64,
-                meta.mixer_track_count as i64,
-                plugins_json,
-                warnings_json,
-                meta.fl_version,
-                parsed_at,
-            ],
-        )
-        .unwrap();
-    }
-
-    update_path_index(db, path, hash, file_size, mtime);
+    conn.execute(
+        "INSERT INTO files (hash, path, file_size, mtime, bpm, time_sig_num, time_sig_den,
+                            channel_count, pattern_count, mixer_track_count, plugins_json,
+                            warnings_json, fl_version, title, artist, genre, comments,
+                            url, created_at, time_spent_secs, patterns_json,
+                            song_length_bars, song_length_secs, header_format, ppq,
+                            automation_json, routing_json, parsed_at, parser_version)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16,
+                 ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?28, ?29)
+         ON CONFLICT(hash) DO UPDATE SET
+            path = excluded.path,
+            file_size = excluded.file_size,
+            mtime = excluded.mtime,
+            bpm = excluded.bpm,
+            time_sig_num = excluded.time_sig_num,
+            time_sig_den = excluded.time_sig_den,
+            channel_count = excluded.channel_count,
+            pattern_count = excluded.pattern_count,
+            mixer_track_count = excluded.mixer_track_count,
+            plugins_json = excluded.plugins_json,
+            warnings_json = excluded.warnings_json,
+            fl_version = excluded.fl_version,
+            title = excluded.title,
+            artist = excluded.artist,
+            genre = excluded.genre,
+            comments = excluded.comments,
+            url = excluded.url,
+            created_at = excluded.created_at,
+            time_spent_secs = excluded.time_spent_secs,
+            patterns_json = excluded.patterns_json,
+            song_length_bars = excluded.song_length_bars,
+            song_length_secs = excluded.song_length_secs,
+            header_format = excluded.header_format,
+            ppq = excluded.ppq,
+            automation_json = excluded.automation_json,
+            routing_json = excluded.routing_json,
+            parsed_at = excluded.parsed_at,
+            parser_version = excluded.parser_version",
+        rusqlite::params![
+            hash,
+            path,
+            file_size,
+            mtime,
+            meta.bpm.map(|b| b as f64),
+            meta.time_sig_num.map(|n| n as i64),
+            meta.time_sig_den.map(|d| d as i64),
+            meta.channel_count as i64,
+            meta.pattern_count as i64,
+            meta.mixer_track_count as i64,
+            plugins_json,
+            warnings_json,
+            meta.fl_version,
+            meta.title,
+            meta.artist,
+            meta.genre,
+            meta.comments,
+            meta.url,
+            // Wall-clock creation time stored as if it were UTC
+            meta.created_at.map(|d| d.and_utc().timestamp()),
+            meta.time_spent.map(|d| d.num_seconds()),
+            patterns_json,
+            meta.song_length_bars.map(|b| b as f64),
+            meta.song_length_secs.map(|s| s as f64),
+            meta.format as i64,
+            meta.ppq as i64,
+            automation_json,
+            routing_json,
+            parsed_at,
+            PARSER_VERSION,
+        ],
+    )?;
+    replace_samples(conn, hash, meta)?;
+    replace_plugin_refs(conn, hash, meta)?;
+    replace_warnings(conn, hash, &meta.warnings)?;
+    replace_unknown_events(conn, hash, &meta.unknown_events)?;
+    write_path_index(conn, path, hash, file_size, mtime, kind)
 }
 
-pub fn list_all_files(db: &Mutex<Connection>) -> Vec<FileRecord> {
-    let conn = db.lock().unwrap();
+pub fn list_all_files(db: &Db) -> Result<Vec<FileRecord>, StoreError> {
+    let conn = db.read();
     let mut stmt = conn
         .prepare(
-            "SELECT f.hash, f.path, f.file_size, f.mtime, f.bpm, f.channel_count,
-                    f.plugins_json, f.fl_version
+            "SELECT f.hash, f.path, f.file_size, f.mtime, f.bpm, f.time_sig_num,
+                    f.time_sig_den, f.channel_count, f.plugins_json, f.fl_version,
+                    f.title, f.artist, f.genre, f.comments, f.url, f.created_at,
+                    f.time_spent_secs, f.patterns_json,
+                    f.song_length_bars, f.song_length_secs, f.header_format, f.ppq,
+                    f.automation_json, f.warnings_json, f.kind, f.routing_json
              FROM files f
              ORDER BY f.path ASC",
-        )
-        .unwrap();
+        )?;
 
-    stmt.query_map([], |row| {
+    let files = stmt.query_map([], |row| {
         Ok(FileRecord {
             hash: row.get(0)?,
             path: row.get(1)?,
             file_size: row.get(2)?,
             mtime: row.get(3)?,
             bpm: row.get(4)?,
-            channel_count: row.get(5)?,
-            plugins_json: row.get(6)?,
-            fl_version: row.get(7)?,
+            time_sig_num: row.get(5)?,
+            time_sig_den: row.get(6)?,
+            channel_count: row.get(7)?,
+            plugins_json: row.get(8)?,
+            fl_version: row.get(9)?,
+            title: row.get(10)?,
+            artist: row.get(11)?,
+            genre: row.get(12)?,
+            comments: row.get(13)?,
+            url: row.get(14)?,
+            created_at: row.get(15)?,
+            time_spent_secs: row.get(16)?,
+            patterns_json: row.get(17)?,
+            song_length_bars: row.get(18)?,
+            song_length_secs: row.get(19)?,
+            header_format: row.get(20)?,
+            ppq: row.get(21)?,
+            automation_json: row.get(22)?,
+            warnings_json: row.get(23)?,
+            kind: FileKind::parse(&row.get::<_, String>(24)?),
+            routing_json: row.get(25)?,
         })
-    })
-    .unwrap()
-    .filter_map(|r| r.ok())
-    .collect()
+    })?;
+    Ok(files.collect::<Result<_, _>>()?)
 }
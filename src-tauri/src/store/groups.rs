@@ -1,8 +1,10 @@
+use crate::store::connection::Db;
+use crate::store::error::StoreError;
+use crate::store::files::FileKind;
 use chrono::Utc;
-use rusqlite::Connection;
+use rusqlite::OptionalExtension;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
-use std::sync::Mutex;
 use uuid::Uuid;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -11,6 +13,10 @@ pub struct ConfirmedGroup {
     pub canonical_name: String,
     pub file_hashes: Vec<String>,
     pub ignored_hashes: Vec<String>,
+    /// FL Studio work time of the song: the largest among its non-ignored
+    /// user saves. Each save-as version carries the time of the one before,
+    /// so adding them up would count it again.
+    pub total_time_spent_secs: i64,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -20,9 +26,12 @@ pub struct GroupConfirmation {
     pub ignored_hashes: Vec<String>,
 }
 
-pub fn confirm_groups(db: &Mutex<Connection>, groups: &[GroupConfirmation]) -> Result<(), String> {
-    let conn = db.lock().unwrap();
-    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+pub fn confirm_groups(
+    db: &Db,
+    groups: &[GroupConfirmation],
+) -> Result<(), StoreError> {
+    let conn = db.write();
+    let tx = conn.unchecked_transaction()?;
 
     let now = Utc::now().timestamp();
 
@@ -32,16 +41,14 @@ pub fn confirm_groups(db: &Mutex<Connection>, groups: &[GroupConfirmation]) -> R
         tx.execute(
             "INSERT INTO song_groups (group_id, canonical_name, confirmed_at, is_ignored) VALUES (?1, ?2, ?3, 0)",
             rusqlite::params![group_id, group.canonical_name, now],
-        )
-        .map_err(|e| e.to_string())?;
+        )?;
 
         for hash in &group.file_hashes {
             let is_ignored = if group.ignored_hashes.contains(hash) { 1 } else { 0 };
             tx.execute(
                 "INSERT INTO group_files (hash, group_id, is_ignored, manually_assigned, assigned_at) VALUES (?1, ?2, ?3, 0, ?4)",
                 rusqlite::params![hash, group_id, is_ignored, now],
-            )
-            .map_err(|e| e.to_string())?;
+            )?;
         }
 
         // Insert any ignored hashes not already in file_hashes
@@ -50,102 +57,111 @@ pub fn confirm_groups(db: &Mutex<Connection>, groups: &[GroupConfirmation]) -> R
                 tx.execute(
                     "INSERT OR IGNORE INTO group_files (hash, group_id, is_ignored, manually_assigned, assigned_at) VALUES (?1, ?2, 1, 0, ?3)",
                     rusqlite::params![hash, group_id, now],
-                )
-                .map_err(|e| e.to_string())?;
+                )?;
             }
         }
     }
 
-    tx.commit().map_err(|e| e.to_string())?;
+    tx.commit()?;
     Ok(())
 }
 
-pub fn list_confirmed_groups(db: &Mutex<Connection>) -> Vec<ConfirmedGroup> {
-    let conn = db.lock().unwrap();
+pub fn list_confirmed_groups(db: &Db) -> Result<Vec<ConfirmedGroup>, StoreError> {
+    let conn = db.read();
 
     let mut stmt = conn
         .prepare(
-            "SELECT sg.group_id, sg.canonical_name, gf.hash, gf.is_ignored
+            "SELECT sg.group_id, sg.canonical_name, gf.hash, gf.is_ignored,
+                    COALESCE(f.time_spent_secs, 0), COALESCE(f.kind, 'user')
              FROM song_groups sg
              JOIN group_files gf ON sg.group_id = gf.group_id
+             LEFT JOIN files f ON f.hash = gf.hash
              ORDER BY sg.canonical_name, sg.group_id, gf.hash",
-        )
-        .expect("Failed to prepare list_confirmed_groups query");
+        )?;
 
-    // group_id -> (canonical_name, file_hashes, ignored_hashes)
-    let mut group_map: BTreeMap<String, (String, Vec<String>, Vec<String>)> = BTreeMap::new();
+    // group_id -> (canonical_name, file_hashes, ignored_hashes, total_time_spent_secs)
+    let mut group_map: BTreeMap<String, (String, Vec<String>, Vec<String>, i64)> = BTreeMap::new();
 
-    let rows: Vec<(String, String, String, i64)> = stmt
+    let rows: Vec<(String, String, String, i64, i64, String)> = stmt
         .query_map([], |row| {
             Ok((
                 row.get::<_, String>(0)?,
                 row.get::<_, String>(1)?,
                 row.get::<_, String>(2)?,
                 row.get::<_, i64>(3)?,
+                row.get::<_, i64>(4)?,
+                row.get::<_, String>(5)?,
             ))
-        })
-        .expect("Failed to query group_files")
-        .flatten()
-        .collect();
+        })?
+        .collect::<Result<_, _>>()?;
 
-    for (group_id, canonical_name, hash, is_ignored) in rows {
+    for (group_id, canonical_name, hash, is_ignored, time_spent_secs, kind) in rows {
         let entry = group_map
             .entry(group_id)
-            .or_insert_with(|| (canonical_name, Vec::new(), Vec::new()));
+            .or_insert_with(|| (canonical_name, Vec::new(), Vec::new(), 0));
         if is_ignored == 1 {
             entry.2.push(hash);
         } else {
             entry.1.push(hash);
+            // Autosaves and backups are copies of time already in a user save
+            if FileKind::parse(&kind) == FileKind::User {
+                entry.3 = entry.3.max(time_spent_secs);
+            }
         }
     }
 
-    group_map
+    Ok(group_map
         .into_iter()
-        .map(|(group_id, (canonical_name, file_hashes, ignored_hashes))| ConfirmedGroup {
-            group_id,
-            canonical_name,
-            file_hashes,
-            ignored_hashes,
-        })
-        .collect()
+        .map(
+            |(group_id, (canonical_name, file_hashes, ignored_hashes, total_time_spent_secs))| {
+                ConfirmedGroup {
+                    group_id,
+                    canonical_name,
+                    file_hashes,
+                    ignored_hashes,
+                    total_time_spent_secs,
+                }
+            },
+        )
+        .collect())
 }
 
-pub fn get_group_for_file(db: &Mutex<Connection>, hash: &str) -> Option<String> {
-    let conn = db.lock().unwrap();
-    conn.query_row(
-        "SELECT group_id FROM group_files WHERE hash = ?1 AND is_ignored = 0 LIMIT 1",
-        [hash],
-        |row| row.get(0),
-    )
-    .ok()
+pub fn get_group_for_file(
+    db: &Db,
+    hash: &str,
+) -> Result<Option<String>, StoreError> {
+    let conn = db.read();
+    let group_id = conn
+        .query_row(
+            "SELECT group_id FROM group_files WHERE hash = ?1 AND is_ignored = 0 LIMIT 1",
+            [hash],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(group_id)
 }
 
-pub fn has_confirmed_groups(db: &Mutex<Connection>) -> bool {
-    let conn = db.lock().unwrap();
-    let count: i64 = conn
-        .query_row("SELECT COUNT(*) FROM song_groups", [], |row| row.get(0))
-        .unwrap_or(0);
-    count > 0
+pub fn has_confirmed_groups(db: &Db) -> Result<bool, StoreError> {
+    let conn = db.read();
+    let count: i64 = conn.query_row("SELECT COUNT(*) FROM song_groups", [], |row| row.get(0))?;
+    Ok(count > 0)
 }
 
-pub fn mark_file_ignored(db: &Mutex<Connection>, hash: &str) -> Result<(), String> {
-    let conn = db.lock().unwrap();
+pub fn mark_file_ignored(db: &Db, hash: &str) -> Result<(), StoreError> {
+    let conn = db.write();
     conn.execute(
         "UPDATE group_files SET is_ignored = 1 WHERE hash = ?1",
         [hash],
-    )
-    .map_err(|e| e.to_string())?;
+    )?;
     Ok(())
 }
 
-pub fn clear_all_groups(db: &Mutex<Connection>) -> Result<(), String> {
-    let conn = db.lock().unwrap();
-    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
-    tx.execute("DELETE FROM group_files", [])
-        .map_err(|e| e.to_string())?;
-    tx.execute("DELETE FROM song_groups", [])
-        .map_err(|e| e.to_string())?;
-    tx.commit().map_err(|e| e.to_string())?;
+pub fn clear_all_groups(db: &Db) -> Result<(), StoreError> {
+    let conn = db.write();
+    let tx = conn.unchecked_transaction()?;
+    tx.execute("DELETE FROM group_files", [])?;
+    tx.execute("DELETE FROM song_groups", [])?;
+    tx.commit()?;
     Ok(())
 }
 
@@ -160,7 +176,7 @@ mod tests {
         let dir = tempdir().unwrap();
         let db = init_db(dir.path()).unwrap();
         {
-            let conn = db.lock().unwrap();
+            let conn = db.write();
             conn.execute(
                 "INSERT INTO files (hash, path, file_size, mtime, parsed_at) VALUES (?1, ?2, ?3, ?4, ?5)",
                 ["abc123", "/test.flp", "1000", "1700000000", "1700000000"],
@@ -173,17 +189,50 @@ mod tests {
             ignored_hashes: vec![],
         }];
         confirm_groups(&db, &groups).unwrap();
-        let confirmed = list_confirmed_groups(&db);
+        let confirmed = list_confirmed_groups(&db).unwrap();
         assert_eq!(confirmed.len(), 1);
         assert_eq!(confirmed[0].canonical_name, "Test Song");
         assert_eq!(confirmed[0].file_hashes.len(), 1);
     }
 
+    #[test]
+    fn test_group_time_spent_counts_overlapping_versions_once() {
+        let dir = tempdir().unwrap();
+        let db = init_db(dir.path()).unwrap();
+        {
+            let conn = db.write();
+            // v2 was saved from v1, so its 5400s include v1's 3600s
+            let files = [
+                ("v1", "3600", "user"),
+                ("v2", "5400", "user"),
+                ("v3", "7200", "user"),
+                ("v2-autosave", "6000", "autosave"),
+            ];
+            for (hash, secs, kind) in files {
+                conn.execute(
+                    "INSERT INTO files (hash, path, file_size, mtime, time_spent_secs, kind,
+                                        parsed_at)
+                     VALUES (?1, ?2, 1000, 1700000000, ?3, ?4, 1700000000)",
+                    [hash, &format!("/{}.flp", hash), secs, kind],
+                )
+                .unwrap();
+            }
+        }
+        let groups = vec![GroupConfirmation {
+            canonical_name: "Song".to_string(),
+            file_hashes: vec!["v1".to_string(), "v2".to_string(), "v2-autosave".to_string()],
+            ignored_hashes: vec!["v3".to_string()],
+        }];
+        confirm_groups(&db, &groups).unwrap();
+        let confirmed = list_confirmed_groups(&db).unwrap();
+        assert_eq!(confirmed[0].total_time_spent_secs, 5400);
+    }
+
     #[test]
     fn test_has_confirmed_groups() {
         let dir = tempdir().unwrap();
         let db = init_db(dir.path()).unwrap();
-        assert!(!has_confirmed_groups(&db));
+        assert!(!has_confirmed_groups(&db).unwrap());
     }
 
     #[test]
@@ -191,7 +240,7 @@ mod tests {
         let dir = tempdir().unwrap();
         let db = init_db(dir.path()).unwrap();
         {
-            let conn = db.lock().unwrap();
+            let conn = db.write();
             conn.execute(
                 "INSERT INTO files (hash, path, file_size, mtime, parsed_at) VALUES (?1, ?2, ?3, ?4, ?5)",
                 ["abc123", "/test.flp", "1000", "1700000000", "1700000000"],
@@ -204,8 +253,8 @@ mod tests {
             ignored_hashes: vec![],
         }];
         confirm_groups(&db, &groups).unwrap();
-        assert!(has_confirmed_groups(&db));
+        assert!(has_confirmed_groups(&db).unwrap());
         clear_all_groups(&db).unwrap();
-        assert!(!has_confirmed_groups(&db));
+        assert!(!has_confirmed_groups(&db).unwrap());
     }
 }
@@ -1,56 +1,346 @@
-use rusqlite::{Connection, Result};
-
-pub fn run_migrations(conn: &Connection) -> Result<()> {
-    conn.execute_batch(
-        "
-        CREATE TABLE IF NOT EXISTS files (
-            hash              TEXT PRIMARY KEY,
-            path              TEXT NOT NULL,
-            file_size         INTEGER NOT NULL,
-            mtime             INTEGER NOT NULL,
-            bpm               REAL,
-            time_sig_num      INTEGER,
-            time_sig_den      INTEGER,
-            channel_count     INTEGER,
-            pattern_count     INTEGER,
-            mixer_track_count INTEGER,
-            plugins_json      TEXT,
-            warnings_json     TEXT,
-            fl_version        TEXT,
-            parsed_at         INTEGER NOT NULL
-        );
-
-        CREATE TABLE IF NOT EXISTS path_index (
-            path      TEXT PRIMARY KEY,
-            hash      TEXT NOT NULL,
-            file_size INTEGER NOT NULL,
-            mtime     INTEGER NOT NULL,
-            FOREIGN KEY (hash) REFERENCES files(hash)
-        );
-
-        CREATE TABLE IF NOT EXISTS settings (
-            key   TEXT PRIMARY KEY,
-            value TEXT NOT NULL
-        );
-
-        CREATE TABLE IF NOT EXISTS song_groups (
-            group_id       TEXT PRIMARY KEY,
-            canonical_name TEXT NOT NULL,
-            confirmed_at   INTEGER NOT NULL,
-            is_ignored     INTEGER NOT NULL DEFAULT 0
-        );
-
-        CREATE TABLE IF NOT EXISTS group_files (
-            hash              TEXT NOT NULL,
-            group_id          TEXT NOT NULL,
-            is_ignored        INTEGER NOT NULL DEFAULT 0,
-            manually_assigned INTEGER NOT NULL DEFAULT 0,
-            assigned_at       INTEGER NOT NULL,
-            PRIMARY KEY (hash, group_id),
-            FOREIGN KEY (hash) REFERENCES files(hash),
-            FOREIGN KEY (group_id) REFERENCES song_groups(group_id)
-        );
-        ",
+use crate::store::error::StoreError;
+use rusqlite::Connection;
+use std::path::{Path, PathBuf};
+
+/// Schema changes in order. Entry `n` takes a database from version `n` to
+/// `n + 1`, tracked in `PRAGMA user_version`. Never edit a released entry;
+/// append a new one instead.
+///
+/// Version 1 is the original schema. Databases created before versioning
+/// report version 0 but already hold it, which its `IF NOT EXISTS` clauses
+/// allow for.
+const MIGRATIONS: &[&str] = &[
+    // 1: original schema
+    "
+    CREATE TABLE IF NOT EXISTS files (
+        hash              TEXT PRIMARY KEY,
+        path              TEXT NOT NULL,
+        file_size         INTEGER NOT NULL,
+        mtime             INTEGER NOT NULL,
+        bpm               REAL,
+        time_sig_num      INTEGER,
+        time_sig_den      INTEGER,
+        channel_count     INTEGER,
+        pattern_count     INTEGER,
+        mixer_track_count INTEGER,
+        plugins_json      TEXT,
+        warnings_json     TEXT,
+        fl_version        TEXT,
+        parsed_at         INTEGER NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS path_index (
+        path      TEXT PRIMARY KEY,
+        hash      TEXT NOT NULL,
+        file_size INTEGER NOT NULL,
+        mtime     INTEGER NOT NULL,
+        FOREIGN KEY (hash) REFERENCES files(hash)
+    );
+
+    CREATE TABLE IF NOT EXISTS settings (
+        key   TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS song_groups (
+        group_id       TEXT PRIMARY KEY,
+        canonical_name TEXT NOT NULL,
+        confirmed_at   INTEGER NOT NULL,
+        is_ignored     INTEGER NOT NULL DEFAULT 0
+    );
+
+    CREATE TABLE IF NOT EXISTS group_files (
+        hash              TEXT NOT NULL,
+        group_id          TEXT NOT NULL,
+        is_ignored        INTEGER NOT NULL DEFAULT 0,
+        manually_assigned INTEGER NOT NULL DEFAULT 0,
+        assigned_at       INTEGER NOT NULL,
+        PRIMARY KEY (hash, group_id),
+        FOREIGN KEY (hash) REFERENCES files(hash),
+        FOREIGN KEY (group_id) REFERENCES song_groups(group_id)
+    );
+    ",
+    // 2: project info and work time
+    "
+    ALTER TABLE files ADD COLUMN title TEXT;
+    ALTER TABLE files ADD COLUMN artist TEXT;
+    ALTER TABLE files ADD COLUMN genre TEXT;
+    ALTER TABLE files ADD COLUMN comments TEXT;
+    ALTER TABLE files ADD COLUMN url TEXT;
+    ALTER TABLE files ADD COLUMN created_at INTEGER;
+    ALTER TABLE files ADD COLUMN time_spent_secs INTEGER;
+    ",
+    // 3: channel sample paths
+    "
+    CREATE TABLE samples (
+        hash          TEXT NOT NULL,
+        channel_index INTEGER NOT NULL,
+        channel_name  TEXT NOT NULL,
+        path          TEXT NOT NULL,
+        PRIMARY KEY (hash, channel_index, path),
+        FOREIGN KEY (hash) REFERENCES files(hash)
+    );
+    ",
+    // 4: patterns and song length
+    "
+    ALTER TABLE files ADD COLUMN patterns_json TEXT;
+    ALTER TABLE files ADD COLUMN song_length_bars REAL;
+    ALTER TABLE files ADD COLUMN song_length_secs REAL;
+    ",
+    // 5: header format and PPQ
+    "
+    ALTER TABLE files ADD COLUMN header_format INTEGER;
+    ALTER TABLE files ADD COLUMN ppq INTEGER;
+    ",
+    // 6: plugin identities
+    "
+    CREATE TABLE plugin_refs (
+        hash          TEXT NOT NULL,
+        role          TEXT NOT NULL,
+        display_name  TEXT,
+        internal_name TEXT NOT NULL,
+        vendor        TEXT,
+        format        TEXT NOT NULL,
+        plugin_id     TEXT,
+        plugin_path   TEXT,
+        FOREIGN KEY (hash) REFERENCES files(hash)
+    );
+
+    CREATE INDEX idx_plugin_refs_hash ON plugin_refs(hash);
+    ",
+    // 7: installed plugin inventory
+    "
+    CREATE TABLE installed_plugins (
+        path       TEXT PRIMARY KEY,
+        name       TEXT NOT NULL,
+        vendor     TEXT,
+        format     TEXT NOT NULL,
+        source     TEXT NOT NULL,
+        scanned_at INTEGER NOT NULL
+    );
+    ",
+    // 8: automation clips
+    "
+    ALTER TABLE files ADD COLUMN automation_json TEXT;
+    ",
+    // 9: structured parse warnings
+    "
+    CREATE TABLE parse_warnings (
+        hash     TEXT NOT NULL,
+        seq      INTEGER NOT NULL,
+        code     TEXT NOT NULL,
+        severity TEXT NOT NULL,
+        offset   INTEGER,
+        event_id INTEGER,
+        message  TEXT NOT NULL,
+        PRIMARY KEY (hash, seq),
+        FOREIGN KEY (hash) REFERENCES files(hash)
+    );
+
+    CREATE INDEX idx_parse_warnings_code ON parse_warnings(code);
+    ",
+    // 10: unhandled event census
+    "
+    CREATE TABLE unknown_events (
+        hash       TEXT NOT NULL,
+        event_id   INTEGER NOT NULL,
+        count      INTEGER NOT NULL,
+        min_size   INTEGER NOT NULL,
+        max_size   INTEGER NOT NULL,
+        sample_hex TEXT NOT NULL,
+        PRIMARY KEY (hash, event_id),
+        FOREIGN KEY (hash) REFERENCES files(hash)
+    );
+    ",
+    // 11: samples bundled in zipped projects
+    "
+    CREATE TABLE bundled_files (
+        hash TEXT NOT NULL,
+        path TEXT NOT NULL,
+        PRIMARY KEY (hash, path),
+        FOREIGN KEY (hash) REFERENCES files(hash)
+    );
+    ",
+    // 12: autosave and backup tagging, per path since identical copies share
+    // one files row
+    "
+    ALTER TABLE files ADD COLUMN kind TEXT NOT NULL DEFAULT 'user';
+    ALTER TABLE path_index ADD COLUMN kind TEXT NOT NULL DEFAULT 'user';
+    ",
+    // 13: mixer routing
+    "
+    ALTER TABLE files ADD COLUMN routing_json TEXT;
+    ",
+    // 14: which parser read each project. The columns and tables above are
+    // filled in by the parser, so rows from before this count as stale and
+    // the next scan parses them again
+    "
+    ALTER TABLE files ADD COLUMN parser_version INTEGER NOT NULL DEFAULT 0;
+    ",
+];
+
+/// Schema version this build expects.
+pub const SCHEMA_VERSION: u32 = MIGRATIONS.len() as u32;
+
+pub fn schema_version(conn: &Connection) -> Result<u32, StoreError> {
+    Ok(conn.query_row("PRAGMA user_version", [], |row| row.get(0))?)
+}
+
+/// Where the copy taken before migrating `db_path` away from `version` goes.
+pub fn backup_path(db_path: &Path, version: u32) -> PathBuf {
+    let mut name = db_path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".v{}.bak", version));
+    db_path.with_file_name(name)
+}
+
+/// Copy the database next to itself before migrations change it. Returns the
+/// backup's path, or `None` when there is nothing to migrate or no data yet.
+pub fn backup_before_migrating(
+    conn: &Connection,
+    db_path: &Path,
+) -> Result<Option<PathBuf>, StoreError> {
+    let version = schema_version(conn)?;
+    let has_tables: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table')",
+        [],
+        |row| row.get(0),
     )?;
+    if version >= SCHEMA_VERSION || !has_tables {
+        return Ok(None);
+    }
+
+    let backup = backup_path(db_path, version);
+    // VACUUM INTO refuses to overwrite, and a leftover copy is from an
+    // earlier attempt at the same upgrade
+    if backup.exists() {
+        return Ok(Some(backup));
+    }
+    conn.execute("VACUUM INTO ?1", [backup.to_string_lossy()])?;
+    Ok(Some(backup))
+}
+
+pub fn run_migrations(conn: &Connection) -> Result<(), StoreError> {
+    apply(conn, MIGRATIONS)
+}
+
+/// Apply every migration after the database's version in one transaction, so
+/// a failure leaves the database as it was.
+fn apply(conn: &Connection, migrations: &[&str]) -> Result<(), StoreError> {
+    let version = schema_version(conn)? as usize;
+    if version > migrations.len() {
+        return Err(StoreError::TooNew {
+            version: version as u32,
+            supported: migrations.len() as u32,
+        });
+    }
+    if version == migrations.len() {
+        return Ok(());
+    }
+
+    let tx = conn.unchecked_transaction()?;
+    for (i, sql) in migrations.iter().enumerate().skip(version) {
+        tx.execute_batch(sql)?;
+        tx.pragma_update(None, "user_version", (i + 1) as u32)?;
+    }
+    tx.commit()?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::connection::init_db;
+
+    /// A database as written by the release before versioned migrations.
+    const V1_FIXTURE: &str = include_str!("../../tests/fixtures/schema_v1.sql");
+
+    fn columns(conn: &Connection, table: &str) -> Vec<String> {
+        let mut stmt = conn
+            .prepare(&format!("SELECT name FROM pragma_table_info('{}') ORDER BY name", table))
+            .unwrap();
+        stmt.query_map([], |row| row.get(0))
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect()
+    }
+
+    fn tables(conn: &Connection) -> Vec<String> {
+        let mut stmt = conn
+            .prepare("SELECT name FROM sqlite_master WHERE type = 'table' ORDER BY name")
+            .unwrap();
+        stmt.query_map([], |row| row.get(0))
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_v1_fixture_upgrades_to_head() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("flp-vault.db");
+        Connection::open(&db_path).unwrap().execute_batch(V1_FIXTURE).unwrap();
+
+        let db = init_db(dir.path()).unwrap();
+        let conn = db.write();
+        assert_eq!(schema_version(&conn).unwrap(), SCHEMA_VERSION);
+
+        // Existing rows survive and new columns take their defaults
+        let (path, kind, ppq): (String, String, Option<i64>) = conn
+            .query_row("SELECT path, kind, ppq FROM files", [], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })
+            .unwrap();
+        assert_eq!((path.as_str(), kind.as_str(), ppq), ("/music/Song.flp", "user", None));
+        let groups: i64 =
+            conn.query_row("SELECT COUNT(*) FROM group_files", [], |row| row.get(0)).unwrap();
+        assert_eq!(groups, 1);
+        let parser_version: i64 =
+            conn.query_row("SELECT parser_version FROM files", [], |row| row.get(0)).unwrap();
+        assert_eq!(parser_version, 0, "Upgraded files should be re-parsed on the next scan");
+
+        // The backup is the untouched v1 database
+        let backup = Connection::open(backup_path(&db_path, 0)).unwrap();
+        assert_eq!(columns(&backup, "files").len(), 14);
+        assert!(!tables(&backup).contains(&"samples".to_string()));
+    }
+
+    #[test]
+    fn test_upgraded_schema_matches_fresh() {
+        let fresh = Connection::open_in_memory().unwrap();
+        run_migrations(&fresh).unwrap();
+        let upgraded = Connection::open_in_memory().unwrap();
+        upgraded.execute_batch(V1_FIXTURE).unwrap();
+        run_migrations(&upgraded).unwrap();
+
+        assert_eq!(tables(&fresh), tables(&upgraded));
+        for table in tables(&fresh) {
+            assert_eq!(columns(&fresh, &table), columns(&upgraded, &table), "{}", table);
+        }
+    }
+
+    #[test]
+    fn test_migrations_are_idempotent() {
+        let dir = tempfile::tempdir().unwrap();
+        drop(init_db(dir.path()).unwrap());
+        let db = init_db(dir.path()).unwrap();
+        assert_eq!(schema_version(&db.write()).unwrap(), SCHEMA_VERSION);
+        // A fresh database has nothing worth backing up
+        assert!(!backup_path(&dir.path().join("flp-vault.db"), 0).exists());
+    }
+
+    #[test]
+    fn test_failed_migration_rolls_back() {
+        let conn = Connection::open_in_memory().unwrap();
+        let broken = [MIGRATIONS[0], "ALTER TABLE files ADD COLUMN extra TEXT;", "NOT SQL"];
+        assert!(apply(&conn, &broken).is_err());
+        assert_eq!(schema_version(&conn).unwrap(), 0);
+        assert!(tables(&conn).is_empty());
+    }
+
+    #[test]
+    fn test_newer_database_is_refused() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION + 1).unwrap();
+        assert!(matches!(run_migrations(&conn), Err(StoreError::TooNew { .. })));
+    }
+}
@@ -1,9 +1,19 @@
+pub mod batch;
+pub mod bundles;
 pub mod connection;
+pub mod error;
 pub mod files;
 pub mod groups;
 pub mod migrations;
+pub mod plugins;
+pub mod samples;
 pub mod settings;
+#[cfg(test)]
+pub mod test_support;
+pub mod unknown_events;
+pub mod warnings;
 
-pub use connection::init_db;
-pub use files::{hash_in_cache, is_cached, list_all_files, update_path_index, upsert_file, FileRecord};
+pub use connection::{init_db, Db};
+pub use error::StoreError;
+pub use files::{hash_in_cache, is_cached, list_all_files, FileRecord};
 pub use settings::{get_all_settings, get_setting, set_setting, Settings};
@@ -0,0 +1,246 @@
+use crate::parser::types::{FlpMetadata, PluginIdentity};
+use crate::store::connection::Db;
+use crate::store::error::StoreError;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+
+/// One plugin identity and how many projects use it.
+#[derive(Debug, Clone, Serialize)]
+pub struct PluginUsage {
+    pub internal_name: String,
+    pub vendor: Option<String>,
+    pub format: String,
+    pub plugin_id: Option<String>,
+    pub project_count: i64,
+}
+
+/// One plugin reference in one project.
+#[derive(Debug, Clone, Serialize)]
+pub struct PluginRefRecord {
+    pub hash: String,
+    pub project_path: String,
+    pub role: String,
+    pub display_name: Option<String>,
+    pub internal_name: String,
+    pub vendor: Option<String>,
+    pub format: String,
+    pub plugin_id: Option<String>,
+    pub plugin_path: Option<String>,
+}
+
+/// A plugin binary found on this machine or listed in the user's manifest.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct InstalledPlugin {
+    pub name: String,
+    pub vendor: Option<String>,
+    /// "vst2", "vst3" or "clap".
+    pub format: String,
+    pub path: String,
+    /// "scan" or "manifest".
+    pub source: String,
+}
+
+/// Replace the plugin references stored for a file hash with those in `meta`.
+/// Called by `write_file` inside the caller's transaction.
+pub(crate) fn replace_plugin_refs(
+    conn: &Connection,
+    hash: &str,
+    meta: &FlpMetadata,
+) -> rusqlite::Result<()> {
+    conn.execute("DELETE FROM plugin_refs WHERE hash = ?1", [hash])?;
+    let mut stmt = conn.prepare(
+        "INSERT INTO plugin_refs (hash, role, display_name, internal_name, vendor, format,
+                                  plugin_id, plugin_path)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+    )?;
+    let mut insert = |role: &str, display_name: Option<&str>, plugin: &PluginIdentity| {
+        stmt.execute(rusqlite::params![
+            hash,
+            role,
+            display_name,
+            plugin.internal_name,
+            plugin.vendor,
+            plugin.format.as_str(),
+            plugin.plugin_id,
+            plugin.path,
+        ])
+        .map(|_| ())
+    };
+    for channel in &meta.generators {
+        if let Some(plugin) = &channel.plugin {
+            insert("generator", channel.plugin_name.as_deref(), plugin)?;
+        }
+    }
+    for slot in meta.mixer.iter().flat_map(|insert| insert.slots.iter()) {
+        if let Some(plugin) = &slot.plugin {
+            insert("effect", slot.plugin_name.as_deref(), plugin)?;
+        }
+    }
+    Ok(())
+}
+
+/// Plugin identities across the library, most used first. Renamed
+/// instances of the same plugin count once per project.
+pub fn list_plugin_usage(db: &Db) -> Result<Vec<PluginUsage>, StoreError> {
+    let conn = db.read();
+    let mut stmt = conn
+        .prepare(
+            "SELECT internal_name, MAX(vendor), format, MAX(plugin_id), COUNT(DISTINCT hash)
+             FROM plugin_refs
+             GROUP BY internal_name, format
+             ORDER BY COUNT(DISTINCT hash) DESC, internal_name ASC",
+        )?;
+
+    let rows = stmt.query_map([], |row| {
+        Ok(PluginUsage {
+            internal_name: row.get(0)?,
+            vendor: row.get(1)?,
+            format: row.get(2)?,
+            plugin_id: row.get(3)?,
+            project_count: row.get(4)?,
+        })
+    })?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+pub fn list_all_plugin_refs(db: &Db) -> Result<Vec<PluginRefRecord>, StoreError> {
+    let conn = db.read();
+    let mut stmt = conn
+        .prepare(
+            "SELECT p.hash, f.path, p.role, p.display_name, p.internal_name, p.vendor,
+                    p.format, p.plugin_id, p.plugin_path
+             FROM plugin_refs p
+             JOIN files f ON f.hash = p.hash
+             ORDER BY f.path ASC",
+        )?;
+
+    let rows = stmt.query_map([], |row| {
+        Ok(PluginRefRecord {
+            hash: row.get(0)?,
+            project_path: row.get(1)?,
+            role: row.get(2)?,
+            display_name: row.get(3)?,
+            internal_name: row.get(4)?,
+            vendor: row.get(5)?,
+            format: row.get(6)?,
+            plugin_id: row.get(7)?,
+            plugin_path: row.get(8)?,
+        })
+    })?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+/// Replace the installed-plugin table with a fresh scan result.
+pub fn replace_installed_plugins(
+    db: &Db,
+    plugins: &[InstalledPlugin],
+) -> Result<(), StoreError> {
+    let scanned_at = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64;
+
+    let conn = db.write();
+    let tx = conn.unchecked_transaction()?;
+    tx.execute("DELETE FROM installed_plugins", [])?;
+    {
+        let mut stmt = tx
+            .prepare(
+                "INSERT OR REPLACE INTO installed_plugins
+                    (path, name, vendor, format, source, scanned_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+        for plugin in plugins {
+            stmt.execute(rusqlite::params![
+                plugin.path,
+                plugin.name,
+                plugin.vendor,
+                plugin.format,
+                plugin.source,
+                scanned_at,
+            ])?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+pub fn list_installed_plugins(db: &Db) -> Result<Vec<InstalledPlugin>, StoreError> {
+    let conn = db.read();
+    let mut stmt = conn
+        .prepare(
+            "SELECT name, vendor, format, path, source
+             FROM installed_plugins
+             ORDER BY name COLLATE NOCASE ASC",
+        )?;
+
+    let rows = stmt.query_map([], |row| {
+        Ok(InstalledPlugin {
+            name: row.get(0)?,
+            vendor: row.get(1)?,
+            format: row.get(2)?,
+            path: row.get(3)?,
+            source: row.get(4)?,
+        })
+    })?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::types::{ChannelInfo, PluginFormat};
+    use crate::store::connection::init_db;
+    use crate::store::test_support::{parsed, store_project};
+    use tempfile::tempdir;
+
+    fn meta_with_generator(display_name: &str, internal_name: &str) -> FlpMetadata {
+        FlpMetadata {
+            generators: vec![ChannelInfo {
+                name: "Lead".to_string(),
+                plugin_name: Some(display_name.to_string()),
+                plugin: Some(PluginIdentity {
+                    internal_name: internal_name.to_string(),
+                    format: PluginFormat::Native,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_usage_groups_renamed_plugins() {
+        let dir = tempdir().unwrap();
+        let db = init_db(dir.path()).unwrap();
+
+        store_project(&db, parsed("a", "/a.flp", &meta_with_generator("Lead", "Sytrus")));
+        store_project(&db, parsed("b", "/b.flp", &meta_with_generator("Pluck", "Sytrus")));
+        // Re-parsing a file replaces its references rather than adding to them
+        store_project(&db, parsed("b", "/b.flp", &meta_with_generator("Pluck", "Sytrus")));
+
+        let usage = list_plugin_usage(&db).unwrap();
+        assert_eq!(usage.len(), 1);
+        assert_eq!(usage[0].internal_name, "Sytrus");
+        assert_eq!(usage[0].format, "native");
+        assert_eq!(usage[0].project_count, 2);
+    }
+
+    #[test]
+    fn test_replace_installed_plugins() {
+        let dir = tempdir().unwrap();
+        let db = init_db(dir.path()).unwrap();
+        let plugin = |name: &str| InstalledPlugin {
+            name: name.to_string(),
+            vendor: None,
+            format: "vst3".to_string(),
+            path: format!("/vst3/{}.vst3", name),
+            source: "scan".to_string(),
+        };
+
+        replace_installed_plugins(&db, &[plugin("Serum"), plugin("Pro-Q 3")]).unwrap();
+        replace_installed_plugins(&db, &[plugin("Serum")]).unwrap();
+        assert_eq!(list_installed_plugins(&db).unwrap(), vec![plugin("Serum")]);
+    }
+}
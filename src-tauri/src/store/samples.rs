@@ -0,0 +1,91 @@
+use crate::parser::types::FlpMetadata;
+use crate::store::connection::Db;
+use crate::store::error::StoreError;
+use rusqlite::Connection;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SampleRecord {
+    pub hash: String,
+    pub project_path: String,
+    pub channel_name: String,
+    pub path: String,
+}
+
+/// Replace the sample references stored for a file hash with those in `meta`.
+/// Called by `write_file` inside the caller's transaction.
+pub(crate) fn replace_samples(
+    conn: &Connection,
+    hash: &str,
+    meta: &FlpMetadata,
+) -> rusqlite::Result<()> {
+    conn.execute("DELETE FROM samples WHERE hash = ?1", [hash])?;
+    let mut stmt = conn.prepare(
+        "INSERT OR IGNORE INTO samples (hash, channel_index, channel_name, path)
+         VALUES (?1, ?2, ?3, ?4)",
+    )?;
+    for channel in &meta.generators {
+        for path in &channel.sample_paths {
+            stmt.execute(rusqlite::params![hash, channel.index as i64, channel.name, path])?;
+        }
+    }
+    Ok(())
+}
+
+pub fn list_all_samples(db: &Db) -> Result<Vec<SampleRecord>, StoreError> {
+    let conn = db.read();
+    let mut stmt = conn
+        .prepare(
+            "SELECT s.hash, f.path, s.channel_name, s.path
+             FROM samples s
+             JOIN files f ON f.hash = s.hash
+             ORDER BY f.path ASC, s.channel_index ASC",
+        )?;
+
+    let rows = stmt.query_map([], |row| {
+        Ok(SampleRecord {
+            hash: row.get(0)?,
+            project_path: row.get(1)?,
+            channel_name: row.get(2)?,
+            path: row.get(3)?,
+        })
+    })?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::types::ChannelInfo;
+    use crate::store::connection::init_db;
+    use crate::store::test_support::{parsed, store_project};
+    use tempfile::tempdir;
+
+    fn meta_with_samples(paths: &[&str]) -> FlpMetadata {
+        FlpMetadata {
+            generators: vec![ChannelInfo {
+                name: "Kick".to_string(),
+                sample_paths: paths.iter().map(|p| p.to_string()).collect(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_upsert_replaces_samples() {
+        let dir = tempdir().unwrap();
+        let db = init_db(dir.path()).unwrap();
+
+        let meta = meta_with_samples(&["a.wav", "b.wav"]);
+        store_project(&db, parsed("abc", "/song.flp", &meta));
+        assert_eq!(list_all_samples(&db).unwrap().len(), 2);
+
+        store_project(&db, parsed("abc", "/song.flp", &meta_with_samples(&["c.wav"])));
+        let samples = list_all_samples(&db).unwrap();
+        assert_eq!(samples.len(), 1);
+        assert_eq!(samples[0].path, "c.wav");
+        assert_eq!(samples[0].channel_name, "Kick");
+        assert_eq!(samples[0].project_path, "/song.flp");
+    }
+}
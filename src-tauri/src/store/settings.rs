@@ -1,12 +1,30 @@
-use rusqlite::Connection;
+use crate::store::connection::Db;
+use crate::store::error::StoreError;
+use rusqlite::OptionalExtension;
 use serde::{Deserialize, Serialize};
-use std::sync::Mutex;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Settings {
     pub source_folder: String,
     pub organized_folder: String,
     pub originals_folder: String,
+    /// VST/VST3/CLAP folders scanned for installed plugins.
+    #[serde(default)]
+    pub plugin_folders: Vec<String>,
+    /// Optional JSON manifest listing plugins installed outside those folders.
+    #[serde(default)]
+    pub plugin_manifest: String,
+    /// FL Studio install folder, which `%FLStudioFactoryData%` in sample
+    /// paths stands for.
+    #[serde(default)]
+    pub fl_factory_data_folder: String,
+    /// FL Studio user data folder, which `%FLStudioUserData%` stands for.
+    #[serde(default)]
+    pub fl_user_data_folder: String,
+    /// Threads that read and parse files during a scan; 0 means one per CPU.
+    /// Scans use at most 64 whatever this says.
+    #[serde(default)]
+    pub scan_workers: usize,
 }
 
 fn default_source_folder() -> String {
@@ -38,37 +56,127 @@ fn default_originals_folder() -> String {
         .into_owned()
 }
 
-pub fn get_setting(db: &Mutex<Connection>, key: &str) -> Option<String> {
-    let conn = db.lock().unwrap();
-    conn.query_row(
-        "SELECT value FROM settings WHERE key = ?1",
-        [key],
-        |row| row.get(0),
-    )
-    .ok()
+/// Newest FL Studio install found in the standard location, or where the
+/// current version installs if none is.
+fn default_fl_factory_data_folder() -> String {
+    let (parent, suffix) = if cfg!(target_os = "windows") {
+        let program_files = std::env::var("ProgramFiles")
+            .unwrap_or_else(|_| "C:\\Program Files".to_string());
+        (std::path::Path::new(&program_files).join("Image-Line"), "")
+    } else if cfg!(target_os = "macos") {
+        (std::path::PathBuf::from("/Applications"), ".app")
+    } else {
+        return String::new();
+    };
+    let newest = std::fs::read_dir(&parent)
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.ok())
+        .map(|e| e.file_name().to_string_lossy().into_owned())
+        .filter(|name| name.starts_with("FL Studio") && name.ends_with(suffix))
+        .max()
+        .unwrap_or_else(|| format!("FL Studio 2024{}", suffix));
+    let install = parent.join(newest);
+    let install = if cfg!(target_os = "macos") {
+        install.join("Contents").join("Resources").join("FL")
+    } else {
+        install
+    };
+    install.to_string_lossy().into_owned()
 }
 
-pub fn set_setting(db: &Mutex<Connection>, key: &str, value: &str) {
-    let conn = db.lock().unwrap();
+/// FL Studio's user data folder: Documents\Image-Line\FL Studio.
+fn default_fl_user_data_folder() -> String {
+    let base = dirs::document_dir()
+        .or_else(dirs::home_dir)
+        .unwrap_or_else(|| std::path::PathBuf::from("."));
+    base.join("Image-Line")
+        .join("FL Studio")
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Standard plugin install locations for the current OS.
+fn default_plugin_folders() -> Vec<String> {
+    let folders: Vec<std::path::PathBuf> = if cfg!(target_os = "windows") {
+        let program_files = std::env::var("ProgramFiles")
+            .unwrap_or_else(|_| "C:\\Program Files".to_string());
+        let common = std::path::Path::new(&program_files).join("Common Files");
+        vec![
+            common.join("VST3"),
+            common.join("CLAP"),
+            std::path::Path::new(&program_files).join("VSTPlugins"),
+            std::path::Path::new(&program_files).join("Steinberg").join("VSTPlugins"),
+        ]
+    } else if cfg!(target_os = "macos") {
+        let system = std::path::Path::new("/Library/Audio/Plug-Ins");
+        let mut folders = vec![system.join("VST"), system.join("VST3"), system.join("CLAP")];
+        if let Some(home) = dirs::home_dir() {
+            let user = home.join("Library").join("Audio").join("Plug-Ins");
+            folders.extend([user.join("VST"), user.join("VST3"), user.join("CLAP")]);
+        }
+        folders
+    } else {
+        dirs::home_dir()
+            .map(|home| vec![home.join(".vst"), home.join(".vst3"), home.join(".clap")])
+            .unwrap_or_default()
+    };
+    folders
+        .into_iter()
+        .map(|p| p.to_string_lossy().into_owned())
+        .collect()
+}
+
+pub fn get_setting(db: &Db, key: &str) -> Result<Option<String>, StoreError> {
+    let conn = db.read();
+    let value = conn
+        .query_row(
+            "SELECT value FROM settings WHERE key = ?1",
+            [key],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(value)
+}
+
+pub fn set_setting(db: &Db, key: &str, value: &str) -> Result<(), StoreError> {
+    let conn = db.write();
     conn.execute(
         "INSERT INTO settings (key, value) VALUES (?1, ?2)
          ON CONFLICT(key) DO UPDATE SET value = excluded.value",
         [key, value],
-    )
-    .unwrap();
+    )?;
+    Ok(())
 }
 
-pub fn get_all_settings(db: &Mutex<Connection>) -> Settings {
-    let source_folder = get_setting(db, "source_folder")
+pub fn get_all_settings(db: &Db) -> Result<Settings, StoreError> {
+    let source_folder = get_setting(db, "source_folder")?
         .unwrap_or_else(default_source_folder);
-    let organized_folder = get_setting(db, "organized_folder")
+    let organized_folder = get_setting(db, "organized_folder")?
         .unwrap_or_else(default_organized_folder);
-    let originals_folder = get_setting(db, "originals_folder")
+    let originals_folder = get_setting(db, "originals_folder")?
         .unwrap_or_else(default_originals_folder);
+    // Stored as a JSON array of paths
+    let plugin_folders = get_setting(db, "plugin_folders")?
+        .and_then(|v| serde_json::from_str(&v).ok())
+        .unwrap_or_else(default_plugin_folders);
+    let plugin_manifest = get_setting(db, "plugin_manifest")?.unwrap_or_default();
+    let fl_factory_data_folder = get_setting(db, "fl_factory_data_folder")?
+        .unwrap_or_else(default_fl_factory_data_folder);
+    let fl_user_data_folder = get_setting(db, "fl_user_data_folder")?
+        .unwrap_or_else(default_fl_user_data_folder);
+    let scan_workers = get_setting(db, "scan_workers")?
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
 
-    Settings {
+    Ok(Settings {
         source_folder,
         organized_folder,
         originals_folder,
-    }
+        plugin_folders,
+        plugin_manifest,
+        fl_factory_data_folder,
+        fl_user_data_folder,
+        scan_workers,
+    })
 }
@@ -0,0 +1,26 @@
+//! Test fixtures, written through `ScanBatch` like a scan would.
+
+use crate::parser::types::FlpMetadata;
+use crate::store::batch::{ParsedFile, ScanBatch, ScanWrite};
+use crate::store::connection::Db;
+use crate::store::files::FileKind;
+use std::time::Duration;
+
+/// A user save of `meta` at `path`, with nothing bundled.
+pub fn parsed(hash: &str, path: &str, meta: &FlpMetadata) -> ParsedFile {
+    ParsedFile {
+        hash: hash.to_string(),
+        path: path.to_string(),
+        file_size: 10,
+        mtime: 1,
+        kind: FileKind::User,
+        meta: meta.clone(),
+        bundled: Vec::new(),
+    }
+}
+
+/// Store one parsed project and commit it.
+pub fn store_project(db: &Db, file: ParsedFile) {
+    let mut batch = ScanBatch::new(db, 1, Duration::ZERO);
+    batch.add(ScanWrite::File(Box::new(file))).unwrap();
+}
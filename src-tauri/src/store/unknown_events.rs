@@ -0,0 +1,137 @@
+use crate::parser::types::UnknownEvent;
+use crate::store::connection::Db;
+use crate::store::error::StoreError;
+use rusqlite::Connection;
+use serde::Serialize;
+
+/// One unhandled event ID across the library.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct UnknownEventSummary {
+    pub event_id: i64,
+    pub file_count: i64,
+    pub occurrences: i64,
+    pub min_size: i64,
+    pub max_size: i64,
+    /// Hex of the leading payload bytes from one file.
+    pub sample_hex: String,
+    /// FL Studio versions of the files it appears in.
+    pub fl_versions: Vec<String>,
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Replace the unknown-event histogram stored for a file hash.
+/// Called by `write_file` inside the caller's transaction.
+pub(crate) fn replace_unknown_events(
+    conn: &Connection,
+    hash: &str,
+    events: &[UnknownEvent],
+) -> rusqlite::Result<()> {
+    conn.execute("DELETE FROM unknown_events WHERE hash = ?1", [hash])?;
+    let mut stmt = conn.prepare(
+        "INSERT INTO unknown_events (hash, event_id, count, min_size, max_size, sample_hex)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    )?;
+    for event in events {
+        stmt.execute(rusqlite::params![
+            hash,
+            event.event_id as i64,
+            event.count as i64,
+            event.min_size as i64,
+            event.max_size as i64,
+            to_hex(&event.sample),
+        ])?;
+    }
+    Ok(())
+}
+
+/// Aggregate unknown events across the library, optionally only for files
+/// saved by one FL Studio version.
+pub fn list_unknown_events(
+    db: &Db,
+    fl_version: Option<&str>,
+) -> Result<Vec<UnknownEventSummary>, StoreError> {
+    let conn = db.read();
+    let mut stmt = conn
+        .prepare(
+            "SELECT u.event_id, COUNT(DISTINCT u.hash), SUM(u.count), MIN(u.min_size),
+                    MAX(u.max_size), MIN(u.sample_hex),
+                    GROUP_CONCAT(DISTINCT COALESCE(f.fl_version, ''))
+             FROM unknown_events u
+             JOIN files f ON f.hash = u.hash
+             WHERE ?1 IS NULL OR f.fl_version = ?1
+             GROUP BY u.event_id
+             ORDER BY u.event_id ASC",
+        )?;
+
+    let rows = stmt.query_map([fl_version], |row| {
+        let versions: Option<String> = row.get(6)?;
+        let mut fl_versions: Vec<String> = versions
+            .unwrap_or_default()
+            .split(',')
+            .filter(|v| !v.is_empty())
+            .map(str::to_string)
+            .collect();
+        fl_versions.sort();
+        Ok(UnknownEventSummary {
+            event_id: row.get(0)?,
+            file_count: row.get(1)?,
+            occurrences: row.get(2)?,
+            min_size: row.get(3)?,
+            max_size: row.get(4)?,
+            sample_hex: row.get(5)?,
+            fl_versions,
+        })
+    })?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::types::FlpMetadata;
+    use crate::store::connection::init_db;
+    use crate::store::test_support::{parsed, store_project};
+    use tempfile::tempdir;
+
+    fn meta(version: &str, events: &[(u8, u32)]) -> FlpMetadata {
+        FlpMetadata {
+            fl_version: Some(version.to_string()),
+            unknown_events: events
+                .iter()
+                .map(|&(event_id, count)| UnknownEvent {
+                    event_id,
+                    count,
+                    min_size: 4,
+                    max_size: 8,
+                    sample: vec![0xde, 0xad],
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_unknown_events_aggregated_by_version() {
+        let dir = tempdir().unwrap();
+        let db = init_db(dir.path()).unwrap();
+
+        store_project(&db, parsed("a", "/a.flp", &meta("20.8.4", &[(250, 2)])));
+        store_project(&db, parsed("b", "/b.flp", &meta("21.0.3", &[(250, 1), (251, 5)])));
+
+        let all = list_unknown_events(&db, None).unwrap();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].event_id, 250);
+        assert_eq!(all[0].file_count, 2);
+        assert_eq!(all[0].occurrences, 3);
+        assert_eq!(all[0].sample_hex, "dead");
+        assert_eq!(all[0].fl_versions, vec!["20.8.4", "21.0.3"]);
+
+        let v21 = list_unknown_events(&db, Some("21.0.3")).unwrap();
+        assert_eq!(v21.len(), 2);
+        assert_eq!(v21[0].occurrences, 1);
+        assert!(list_unknown_events(&db, Some("12.0")).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,108 @@
+use crate::parser::types::ParseWarning;
+use crate::store::connection::Db;
+use crate::store::error::StoreError;
+use rusqlite::Connection;
+use serde::Serialize;
+
+/// How many files carry a given warning code.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct WarningCodeCount {
+    pub code: String,
+    pub severity: String,
+    pub file_count: i64,
+}
+
+/// Replace the parse warnings stored for a file hash.
+/// Called by `write_file` inside the caller's transaction.
+pub(crate) fn replace_warnings(
+    conn: &Connection,
+    hash: &str,
+    warnings: &[ParseWarning],
+) -> rusqlite::Result<()> {
+    conn.execute("DELETE FROM parse_warnings WHERE hash = ?1", [hash])?;
+    let mut stmt = conn.prepare(
+        "INSERT INTO parse_warnings (hash, seq, code, severity, offset, event_id, message)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+    )?;
+    for (seq, warning) in warnings.iter().enumerate() {
+        stmt.execute(rusqlite::params![
+            hash,
+            seq as i64,
+            warning.code.as_str(),
+            warning.severity.as_str(),
+            warning.offset.map(|o| o as i64),
+            warning.event_id.map(|e| e as i64),
+            warning.message,
+        ])?;
+    }
+    Ok(())
+}
+
+/// Warning codes present in the library, most widespread first.
+pub fn list_warning_codes(db: &Db) -> Result<Vec<WarningCodeCount>, StoreError> {
+    let conn = db.read();
+    let mut stmt = conn
+        .prepare(
+            "SELECT code, MAX(severity), COUNT(DISTINCT hash)
+             FROM parse_warnings
+             GROUP BY code
+             ORDER BY COUNT(DISTINCT hash) DESC, code ASC",
+        )?;
+
+    let rows = stmt.query_map([], |row| {
+        Ok(WarningCodeCount {
+            code: row.get(0)?,
+            severity: row.get(1)?,
+            file_count: row.get(2)?,
+        })
+    })?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+/// Hashes of files with at least one warning of the given code.
+pub fn hashes_with_warning(db: &Db, code: &str) -> Result<Vec<String>, StoreError> {
+    let conn = db.read();
+    let mut stmt = conn
+        .prepare("SELECT DISTINCT hash FROM parse_warnings WHERE code = ?1 ORDER BY hash")?;
+
+    let rows = stmt.query_map([code], |row| row.get(0))?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::types::{FlpMetadata, WarningCode};
+    use crate::store::connection::init_db;
+    use crate::store::test_support::{parsed, store_project};
+    use tempfile::tempdir;
+
+    fn meta_with(codes: &[WarningCode]) -> FlpMetadata {
+        FlpMetadata {
+            warnings: codes.iter().map(|c| ParseWarning::new(*c, "test")).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_warning_codes_counted_per_file() {
+        let dir = tempdir().unwrap();
+        let db = init_db(dir.path()).unwrap();
+
+        let both = [WarningCode::Truncated, WarningCode::Truncated, WarningCode::MissingBpm];
+        store_project(&db, parsed("a", "/a.flp", &meta_with(&both)));
+        store_project(&db, parsed("b", "/b.flp", &meta_with(&[WarningCode::MissingBpm])));
+
+        let counts = list_warning_codes(&db).unwrap();
+        assert_eq!(counts[0].code, "missing_bpm");
+        assert_eq!(counts[0].file_count, 2);
+        assert_eq!(counts[1].code, "truncated");
+        assert_eq!(counts[1].severity, "error");
+        assert_eq!(counts[1].file_count, 1);
+
+        // Re-parse without problems clears the file's warnings
+        store_project(&db, parsed("a", "/a.flp", &meta_with(&[])));
+        assert!(hashes_with_warning(&db, "truncated").unwrap().is_empty());
+        assert_eq!(hashes_with_warning(&db, "missing_bpm").unwrap(), vec!["b"]);
+    }
+}
@@ -0,0 +1,65 @@
+-- Schema and sample rows from before versioned migrations (user_version 0).
+
+CREATE TABLE IF NOT EXISTS files (
+    hash              TEXT PRIMARY KEY,
+    path              TEXT NOT NULL,
+    file_size         INTEGER NOT NULL,
+    mtime             INTEGER NOT NULL,
+    bpm               REAL,
+    time_sig_num      INTEGER,
+    time_sig_den      INTEGER,
+    channel_count     INTEGER,
+    pattern_count     INTEGER,
+    mixer_track_count INTEGER,
+    plugins_json      TEXT,
+    warnings_json     TEXT,
+    fl_version        TEXT,
+    parsed_at         INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS path_index (
+    path      TEXT PRIMARY KEY,
+    hash      TEXT NOT NULL,
+    file_size INTEGER NOT NULL,
+    mtime     INTEGER NOT NULL,
+    FOREIGN KEY (hash) REFERENCES files(hash)
+);
+
+CREATE TABLE IF NOT EXISTS settings (
+    key   TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS song_groups (
+    group_id       TEXT PRIMARY KEY,
+    canonical_name TEXT NOT NULL,
+    confirmed_at   INTEGER NOT NULL,
+    is_ignored     INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE TABLE IF NOT EXISTS group_files (
+    hash              TEXT NOT NULL,
+    group_id          TEXT NOT NULL,
+    is_ignored        INTEGER NOT NULL DEFAULT 0,
+    manually_assigned INTEGER NOT NULL DEFAULT 0,
+    assigned_at       INTEGER NOT NULL,
+    PRIMARY KEY (hash, group_id),
+    FOREIGN KEY (hash) REFERENCES files(hash),
+    FOREIGN KEY (group_id) REFERENCES song_groups(group_id)
+);
+
+INSERT INTO files (hash, path, file_size, mtime, bpm, time_sig_num, time_sig_den, channel_count,
+                   pattern_count, mixer_track_count, plugins_json, warnings_json, fl_version,
+                   parsed_at)
+VALUES ('abc123', '/music/Song.flp', 2048, 1700000000, 140.0, 4, 4, 8, 3, 5,
+        '["Sytrus"]', '[]', '21.2.3.4004', 1700000100);
+
+INSERT INTO path_index (path, hash, file_size, mtime)
+VALUES ('/music/Song.flp', 'abc123', 2048, 1700000000);
+
+INSERT INTO settings (key, value) VALUES ('source_folder', '/music');
+
+INSERT INTO song_groups (group_id, canonical_name, confirmed_at)
+VALUES ('g1', 'Song', 1700000200);
+
+INSERT INTO group_files (hash, group_id, assigned_at) VALUES ('abc123', 'g1', 1700000200);
@@ -39,6 +39,38 @@ export function resetGroups() {
     return invoke('reset_groups');
 }
 
+export function findMissingSamples() {
+    return invoke('find_missing_samples');
+}
+
+export function listPluginUsage() {
+    return invoke('list_plugin_usage');
+}
+
+export function scanInstalledPlugins() {
+    return invoke('scan_installed_plugins');
+}
+
+export function findMissingPlugins() {
+    return invoke('find_missing_plugins');
+}
+
+export function listWarningCodes() {
+    return invoke('list_warning_codes');
+}
+
+export function listFilesWithWarning(code) {
+    return invoke('list_files_with_warning', { code });
+}
+
+export function listUnknownEvents(flVersion = null) {
+    return invoke('list_unknown_events', { flVersion });
+}
+
+export function dumpFlp(path, format = 'text') {
+    return invoke('dump_flp', { path, format });
+}
+
 export function onScanStarted(callback) {
     return listen('scan:started', callback);
 }
@@ -54,3 +86,7 @@ export function onScanComplete(callback) {
 export function onScanCancelled(callback) {
     return listen('scan:cancelled', callback);
 }
+
+export function onScanFailed(callback) {
+    return listen('scan:failed', callback);
+}
@@ -1,6 +1,9 @@
 // Scan results table with live streaming from scan events
 
-import { onScanStarted, onScanProgress, onScanComplete, onScanCancelled, cancelScan, listScannedFiles } from '../api.js';
+import {
+    onScanStarted, onScanProgress, onScanComplete, onScanCancelled, onScanFailed, cancelScan,
+    listScannedFiles, listWarningCodes, listFilesWithWarning,
+} from '../api.js';
 
 const COLUMNS = [
     { key: 'name',     label: 'Name',     sortFn: (a, b) => a.name.localeCompare(b.name) },
@@ -13,9 +16,11 @@ const COLUMNS = [
 let rows = [];
 let sortKey = 'name';
 let sortDir = 1;
+let warningFilter = null; // { code, hashes: Set } while filtering by warning code
 let scanning = false;
 let scanTotal = 0;
 let scanDone = 0;
+let scanError = null; // message from the last scan:failed, shown until the next scan
 
 let tableEl = null;
 let tbodyEl = null;
@@ -23,12 +28,22 @@ let progressEl = null;
 let progressBarEl = null;
 let progressTextEl = null;
 let emptyStateEl = null;
+let filterSelectEl = null;
 
 function parsePlugins(plugins_json) {
     if (!plugins_json) return [];
     try { return JSON.parse(plugins_json); } catch { return []; }
 }
 
+// Older cache rows stored warnings as plain strings
+function parseWarnings(warnings) {
+    let list = warnings ?? [];
+    if (typeof list === 'string') {
+        try { list = JSON.parse(list); } catch { list = []; }
+    }
+    return list.map(w => (typeof w === 'string' ? { code: 'unknown', message: w } : w));
+}
+
 function formatDate(mtime) {
     if (!mtime) return '\u2014';
     return new Date(mtime * 1000).toLocaleDateString(undefined, {
@@ -50,7 +65,7 @@ function rowDataFromRecord(record) {
         pluginsSummary,
         mtime: record.mtime,
         formattedDate: formatDate(record.mtime),
-        warnings: [],
+        warnings: parseWarnings(record.warnings_json),
     };
 }
 
@@ -68,7 +83,7 @@ function rowDataFromProgress(payload) {
         pluginsSummary,
         mtime: payload.mtime,
         formattedDate: formatDate(payload.mtime),
-        warnings: payload.warnings ?? [],
+        warnings: parseWarnings(payload.warnings),
     };
 }
 
@@ -93,17 +108,18 @@ function makeEl(tag, props) {
 
 function renderRow(row) {
     const tr = makeEl('tr');
-    if (row.warnings && row.warnings.length > 0) {
+    const warningText = row.warnings.map(w => w.message).join('\n');
+    if (row.warnings.length > 0) {
         tr.classList.add('has-warnings');
-        tr.title = row.warnings.join('\n');
+        tr.title = warningText;
     }
 
     // Name column
     const tdName = makeEl('td', { className: 'col-name', title: row.path });
-    if (row.warnings && row.warnings.length > 0) {
+    if (row.warnings.length > 0) {
         const warnSpan = makeEl('span', {
             className: 'warning-icon',
-            title: row.warnings.join('\n'),
+            title: warningText,
             textContent: '\u26a0\ufe0f',
         });
         tdName.appendChild(warnSpan);
@@ -151,10 +167,15 @@ function renderRow(row) {
     return tr;
 }
 
+function visibleRows() {
+    if (!warningFilter) return rows;
+    return rows.filter(r => warningFilter.hashes.has(r.hash));
+}
+
 function sortedRows() {
     const col = COLUMNS.find(c => c.key === sortKey);
-    if (!col) return rows;
-    return [...rows].sort((a, b) => col.sortFn(a, b) * sortDir);
+    if (!col) return visibleRows();
+    return [...visibleRows()].sort((a, b) => col.sortFn(a, b) * sortDir);
 }
 
 function renderTable() {
@@ -180,6 +201,9 @@ function updateProgressBar() {
         progressTextEl.textContent = `Scanning\u2026 ${scanDone}/${scanTotal} files`;
         const pct = scanTotal > 0 ? (scanDone / scanTotal) * 100 : 0;
         progressBarEl.style.width = `${pct}%`;
+    } else if (scanError) {
+        progressEl.style.display = 'flex';
+        progressTextEl.textContent = `Scan stopped after ${scanDone}/${scanTotal} files: ${scanError}`;
     } else {
         progressEl.style.display = 'none';
     }
@@ -225,6 +249,56 @@ function buildProgressBar(container) {
     return { wrapper, fill, text };
 }
 
+async function setWarningFilter(code) {
+    if (!code) {
+        warningFilter = null;
+    } else {
+        try {
+            const hashes = await listFilesWithWarning(code);
+            warningFilter = { code, hashes: new Set(hashes) };
+        } catch (err) {
+            console.error('Failed to filter by warning:', err);
+            warningFilter = null;
+        }
+    }
+    renderTable();
+}
+
+async function refreshWarningCodes() {
+    if (!filterSelectEl) return;
+    let codes = [];
+    try {
+        codes = await listWarningCodes();
+    } catch (err) {
+        console.error('Failed to load warning codes:', err);
+    }
+    while (filterSelectEl.firstChild) filterSelectEl.removeChild(filterSelectEl.firstChild);
+    const all = makeEl('option', { textContent: 'All files' });
+    all.value = '';
+    filterSelectEl.appendChild(all);
+    codes.forEach(c => {
+        const opt = makeEl('option', { textContent: `${c.code.replace(/_/g, ' ')} (${c.file_count})` });
+        opt.value = c.code;
+        filterSelectEl.appendChild(opt);
+    });
+    // Keep the active filter if its code is still present
+    const current = warningFilter?.code ?? '';
+    filterSelectEl.value = codes.some(c => c.code === current) ? current : '';
+    filterSelectEl.parentNode.style.display = codes.length > 0 ? '' : 'none';
+    await setWarningFilter(filterSelectEl.value);
+}
+
+function buildWarningFilter(container) {
+    const wrapper = makeEl('div', { className: 'warning-filter', style: { display: 'none' } });
+    const label = makeEl('label', { className: 'warning-filter-label', textContent: 'Warnings:' });
+    wrapper.appendChild(label);
+    const select = makeEl('select', { className: 'warning-filter-select' });
+    select.addEventListener('change', () => setWarningFilter(select.value));
+    wrapper.appendChild(select);
+    container.appendChild(wrapper);
+    return select;
+}
+
 function buildTable(container) {
     const table = makeEl('table', { className: 'scan-table', style: { display: 'none' } });
 
@@ -255,6 +329,8 @@ export function init(container, { emptyState }) {
     progressBarEl = fill;
     progressTextEl = text;
 
+    filterSelectEl = buildWarningFilter(container);
+
     const { table, tbody } = buildTable(container);
     tableEl = table;
     tbodyEl = tbody;
@@ -265,6 +341,7 @@ export function init(container, { emptyState }) {
     // Scan event listeners
     onScanStarted(({ payload }) => {
         scanning = true;
+        scanError = null;
         scanTotal = payload.total;
         scanDone = 0;
         rows = [];
@@ -290,6 +367,7 @@ export function init(container, { emptyState }) {
         scanning = false;
         updateProgressBar();
         updateEmptyState();
+        refreshWarningCodes();
     }).catch(console.error);
 
     onScanCancelled(() => {
@@ -297,6 +375,14 @@ export function init(container, { emptyState }) {
         updateProgressBar();
         updateEmptyState();
     }).catch(console.error);
+
+    onScanFailed(({ payload }) => {
+        scanning = false;
+        scanDone = payload.done;
+        scanError = payload.message;
+        updateProgressBar();
+        updateEmptyState();
+    }).catch(console.error);
 }
 
 export async function loadFromCache() {
@@ -304,6 +390,7 @@ export async function loadFromCache() {
         const records = await listScannedFiles();
         rows = records.map(rowDataFromRecord);
         renderTable();
+        await refreshWarningCodes();
     } catch (err) {
         console.error('Failed to load cached files:', err);
     }
@@ -6,7 +6,16 @@ const invoke = window.__TAURI__?.core?.invoke;
 
 let panelEl = null;
 let overlayEl = null;
-let currentSettings = { source_folder: '', organized_folder: '', originals_folder: '' };
+let currentSettings = {
+    source_folder: '',
+    organized_folder: '',
+    originals_folder: '',
+    plugin_folders: [],
+    plugin_manifest: '',
+    fl_factory_data_folder: '',
+    fl_user_data_folder: '',
+    scan_workers: 0,
+};
 let previousSourceFolder = '';
 let onRescan = null;
 
@@ -24,7 +33,7 @@ function makeEl(tag, props) {
     return el;
 }
 
-function buildFolderRow(label, key, placeholder) {
+function buildFolderRow(label, key, placeholder, { directory = true } = {}) {
     const row = makeEl('div', { className: 'settings-row' });
 
     const lbl = makeEl('label', { className: 'settings-label', textContent: label });
@@ -51,7 +60,7 @@ function buildFolderRow(label, key, placeholder) {
     browseBtn.addEventListener('click', async () => {
         try {
             const options = Object.freeze({
-                directory: true,
+                directory,
                 title: `Select ${label}`,
                 defaultPath: input.value || undefined,
             });
@@ -70,6 +79,48 @@ function buildFolderRow(label, key, placeholder) {
     return { row, input };
 }
 
+// Plugin folders are edited as one path per line
+function buildPluginFoldersRow() {
+    const row = makeEl('div', { className: 'settings-row' });
+    row.appendChild(makeEl('label', { className: 'settings-label', textContent: 'Plugin Folders' }));
+
+    const textarea = makeEl('textarea', {
+        className: 'settings-plugin-folders',
+        placeholder: 'One VST/VST3/CLAP folder per line\u2026',
+    });
+    textarea.rows = 4;
+    textarea.value = (currentSettings.plugin_folders ?? []).join('\n');
+    textarea.addEventListener('input', () => {
+        currentSettings.plugin_folders = textarea.value
+            .split('\n')
+            .map(line => line.trim())
+            .filter(line => line.length > 0);
+    });
+    row.appendChild(textarea);
+    return row;
+}
+
+// Scan worker threads; 0 lets the app use one per CPU
+function buildScanWorkersRow() {
+    const row = makeEl('div', { className: 'settings-row' });
+    row.appendChild(makeEl('label', { className: 'settings-label', textContent: 'Scan Workers' }));
+
+    const input = makeEl('input', {
+        className: 'settings-scan-workers',
+        type: 'number',
+        title: 'Files read and parsed at once during a scan (0 = one per CPU)',
+        value: currentSettings.scan_workers ?? 0,
+    });
+    input.min = 0;
+    input.max = 64;
+    input.addEventListener('input', () => {
+        const n = parseInt(input.value, 10);
+        currentSettings.scan_workers = Number.isFinite(n) && n > 0 ? Math.min(n, 64) : 0;
+    });
+    row.appendChild(input);
+    return row;
+}
+
 function showWarnings(warnings) {
     let warningsEl = panelEl.querySelector('.settings-warnings');
     if (!warningsEl) {
@@ -128,6 +179,33 @@ function buildPanel() {
     );
     body.appendChild(originalsRow);
 
+    body.appendChild(buildPluginFoldersRow());
+
+    const { row: manifestRow } = buildFolderRow(
+        'Plugin Manifest',
+        'plugin_manifest',
+        'Optional JSON list of other plugins\u2026',
+        { directory: false },
+    );
+    body.appendChild(manifestRow);
+
+    // Folders FL Studio's %FLStudioFactoryData% / %FLStudioUserData% sample paths point into
+    const { row: factoryRow } = buildFolderRow(
+        'FL Studio Install',
+        'fl_factory_data_folder',
+        'FL Studio program folder\u2026',
+    );
+    body.appendChild(factoryRow);
+
+    const { row: userDataRow } = buildFolderRow(
+        'FL Studio User Data',
+        'fl_user_data_folder',
+        'Documents\\Image-Line\\FL Studio\u2026',
+    );
+    body.appendChild(userDataRow);
+
+    body.appendChild(buildScanWorkersRow());
+
     panelEl.appendChild(body);
 
     // Footer
@@ -187,6 +265,10 @@ export async function show() {
                     input.value = currentSettings[key];
                 }
             });
+            const foldersEl = panelEl.querySelector('.settings-plugin-folders');
+            if (foldersEl) foldersEl.value = (currentSettings.plugin_folders ?? []).join('\n');
+            const workersEl = panelEl.querySelector('.settings-scan-workers');
+            if (workersEl) workersEl.value = currentSettings.scan_workers ?? 0;
         }
 
         showWarnings([]);
@@ -245,7 +245,12 @@ function renderGroupCard(group, index, totalCount) {
             tr.appendChild(tdCheck);
         }
 
-        tr.appendChild(makeEl('td', { title: detail.path || hash, textContent: getFilename(detail.path || hash) }));
+        const tdName = makeEl('td', { title: detail.path || hash, textContent: getFilename(detail.path || hash) });
+        // Autosave/backup copies made by FL Studio, listed after the user's saves
+        if (detail.kind && detail.kind !== 'user') {
+            tdName.appendChild(makeEl('span', { className: 'file-kind', textContent: detail.kind }));
+        }
+        tr.appendChild(tdName);
         tr.appendChild(makeEl('td', {
             textContent: detail.bpm != null ? Number(detail.bpm).toFixed(0) : '\u2014',
         }));